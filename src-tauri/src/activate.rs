@@ -1,40 +1,21 @@
 use serde::{Deserialize, Serialize};
-use std::env;
 use std::fs;
 use std::path::PathBuf;
 use tauri::{AppHandle, Manager};
 use uuid::Uuid;
 
-fn get_payment_endpoint() -> Result<String, String> {
-    if let Ok(endpoint) = env::var("PAYMENT_ENDPOINT") {
-        return Ok(endpoint);
-    }
-    
-    match option_env!("PAYMENT_ENDPOINT") {
-        Some(endpoint) => Ok(endpoint.to_string()),
-        None => Err("PAYMENT_ENDPOINT environment variable not set. Please ensure it's set during the build process.".to_string())
-    }
-}
-
-fn get_api_access_key() -> Result<String, String> {
-     if let Ok(key) = env::var("API_ACCESS_KEY") {
-        return Ok(key);
-    }
-    
-     match option_env!("API_ACCESS_KEY") {
-        Some(key) => Ok(key.to_string()),
-        None => Err("API_ACCESS_KEY environment variable not set. Please ensure it's set during the build process.".to_string())
-    }
-}
+use crate::endpoints;
+use crate::error::ApiError;
+use crate::http;
 
 // Secure storage functions using Tauri's app data directory
-fn get_secure_storage_path(app: &AppHandle) -> Result<PathBuf, String> {
+fn get_secure_storage_path(app: &AppHandle) -> Result<PathBuf, ApiError> {
     let app_data_dir = app.path().app_data_dir()
-        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+        .map_err(|e| ApiError::Storage(format!("Failed to get app data directory: {}", e)))?;
     
     // Create the directory if it doesn't exist
     fs::create_dir_all(&app_data_dir)
-        .map_err(|e| format!("Failed to create app data directory: {}", e))?;
+        .map_err(|e| ApiError::Storage(format!("Failed to create app data directory: {}", e)))?;
     
     Ok(app_data_dir.join("secure_storage.json"))
 }
@@ -60,12 +41,12 @@ pub struct StorageResult {
 }
 
 #[tauri::command]
-pub async fn secure_storage_save(app: AppHandle, items: Vec<StorageItem>) -> Result<(), String> {
+pub async fn secure_storage_save(app: AppHandle, items: Vec<StorageItem>) -> Result<(), ApiError> {
     let storage_path = get_secure_storage_path(&app)?;
     
     let mut storage = if storage_path.exists() {
         let content = fs::read_to_string(&storage_path)
-            .map_err(|e| format!("Failed to read storage file: {}", e))?;
+            .map_err(|e| ApiError::Storage(format!("Failed to read storage file: {}", e)))?;
         serde_json::from_str(&content).unwrap_or_default()
     } else {
         SecureStorage::default()
@@ -76,21 +57,21 @@ pub async fn secure_storage_save(app: AppHandle, items: Vec<StorageItem>) -> Res
             "extab_license_key" => storage.license_key = Some(item.value),
             "extab_instance_id" => storage.instance_id = Some(item.value),
             "selected_extab_model" => storage.selected_extab_model = Some(item.value),
-            _ => return Err(format!("Invalid storage key: {}", item.key)),
+            _ => return Err(ApiError::Storage(format!("Invalid storage key: {}", item.key))),
         }
     }
     
     let content = serde_json::to_string(&storage)
-        .map_err(|e| format!("Failed to serialize storage: {}", e))?;
+        .map_err(|e| ApiError::Storage(format!("Failed to serialize storage: {}", e)))?;
     
     fs::write(&storage_path, content)
-        .map_err(|e| format!("Failed to write storage file: {}", e))?;
+        .map_err(|e| ApiError::Storage(format!("Failed to write storage file: {}", e)))?;
     
     Ok(())
 }
 
 #[tauri::command]
-pub async fn secure_storage_get(app: AppHandle) -> Result<StorageResult, String> {
+pub async fn secure_storage_get(app: AppHandle) -> Result<StorageResult, ApiError> {
     let storage_path = get_secure_storage_path(&app)?;
     
     if !storage_path.exists() {
@@ -102,10 +83,10 @@ pub async fn secure_storage_get(app: AppHandle) -> Result<StorageResult, String>
     }
     
     let content = fs::read_to_string(&storage_path)
-        .map_err(|e| format!("Failed to read storage file: {}", e))?;
+        .map_err(|e| ApiError::Storage(format!("Failed to read storage file: {}", e)))?;
     
     let storage: SecureStorage = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse storage file: {}", e))?;
+        .map_err(|e| ApiError::Storage(format!("Failed to parse storage file: {}", e)))?;
     
     Ok(StorageResult {
         license_key: storage.license_key,
@@ -115,7 +96,7 @@ pub async fn secure_storage_get(app: AppHandle) -> Result<StorageResult, String>
 }
 
 #[tauri::command]
-pub async fn secure_storage_remove(app: AppHandle, keys: Vec<String>) -> Result<(), String> {
+pub async fn secure_storage_remove(app: AppHandle, keys: Vec<String>) -> Result<(), ApiError> {
     let storage_path = get_secure_storage_path(&app)?;
     
     if !storage_path.exists() {
@@ -123,25 +104,25 @@ pub async fn secure_storage_remove(app: AppHandle, keys: Vec<String>) -> Result<
     }
     
     let content = fs::read_to_string(&storage_path)
-        .map_err(|e| format!("Failed to read storage file: {}", e))?;
+        .map_err(|e| ApiError::Storage(format!("Failed to read storage file: {}", e)))?;
     
     let mut storage: SecureStorage = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse storage file: {}", e))?;
+        .map_err(|e| ApiError::Storage(format!("Failed to parse storage file: {}", e)))?;
     
     for key in keys {
         match key.as_str() {
             "extab_license_key" => storage.license_key = None,
             "extab_instance_id" => storage.instance_id = None,
             "selected_extab_model" => storage.selected_extab_model = None,
-            _ => return Err(format!("Invalid storage key: {}", key)),
+            _ => return Err(ApiError::Storage(format!("Invalid storage key: {}", key))),
         }
     }
     
     let content = serde_json::to_string(&storage)
-        .map_err(|e| format!("Failed to serialize storage: {}", e))?;
+        .map_err(|e| ApiError::Storage(format!("Failed to serialize storage: {}", e)))?;
     
     fs::write(&storage_path, content)
-        .map_err(|e| format!("Failed to write storage file: {}", e))?;
+        .map_err(|e| ApiError::Storage(format!("Failed to write storage file: {}", e)))?;
     
     Ok(())
 }
@@ -174,11 +155,31 @@ pub struct CheckoutResponse {
     error: Option<String>,
 }
 
+// These endpoints report failures in the JSON body, so the body is parsed even
+// for error statuses and only falls back to the status when it doesn't fit.
+async fn parse_json_response<T: serde::de::DeserializeOwned>(
+    response: reqwest::Response,
+    context: &str,
+) -> Result<T, ApiError> {
+    let status = response.status();
+    let body = response
+        .text()
+        .await
+        .map_err(|e| ApiError::from_reqwest(context, e))?;
+
+    match serde_json::from_str(&body) {
+        Ok(parsed) => Ok(parsed),
+        Err(_) if !status.is_success() => Err(ApiError::from_response(status, &body)),
+        Err(e) => Err(ApiError::Parse(format!("Failed to parse {} response: {}", context, e))),
+    }
+}
+
 #[tauri::command]
-pub async fn activate_license_api(license_key: String) -> Result<ActivationResponse, String> {
-    // Get payment endpoint and API access key from environment
-    let payment_endpoint = get_payment_endpoint()?;
-    let api_access_key = get_api_access_key()?;
+pub async fn activate_license_api(app: AppHandle, license_key: String) -> Result<ActivationResponse, ApiError> {
+    // Resolve the configured payment backend
+    let endpoints = endpoints::endpoints(&app);
+    let payment_endpoint = endpoints.payment_base()?;
+    endpoints.check_auth()?;
     
     // Generate UUID for instance name
     let instance_name = Uuid::new_v4().to_string();
@@ -190,48 +191,17 @@ pub async fn activate_license_api(license_key: String) -> Result<ActivationRespo
     };
     
     // Make HTTP request to activation endpoint with authorization header
-    let client = reqwest::Client::new();
+    let client = http::client(&app);
     let url = format!("{}/activate", payment_endpoint);
     
-    let response = client
-        .post(&url)
-        .header("Content-Type", "application/json")
-        .header("Authorization", format!("Bearer {}", api_access_key))
+    let response = endpoints
+        .authorize(client.post(&url).header("Content-Type", "application/json"))
         .json(&activation_request)
         .send()
         .await
-        .map_err(|e| {
-            let error_msg = format!("{}", e);
-            if error_msg.contains("url (") {
-                // Remove the URL part from the error message
-                let parts: Vec<&str> = error_msg.split(" for url (").collect();
-                if parts.len() > 1 {
-                    format!("Failed to make chat request: {}", parts[0])
-                } else {
-                    format!("Failed to make chat request: {}", error_msg)
-                }
-            } else {
-                format!("Failed to make chat request: {}", error_msg)
-            }
-        })?;
+        .map_err(|e| ApiError::from_reqwest("activation", e))?;
     
-    let activation_response: ActivationResponse = response
-        .json()
-        .await
-        .map_err(|e| {
-            let error_msg = format!("{}", e);
-            if error_msg.contains("url (") {
-                // Remove the URL part from the error message
-                let parts: Vec<&str> = error_msg.split(" for url (").collect();
-                if parts.len() > 1 {
-                    format!("Failed to make chat request: {}", parts[0])
-                } else {
-                    format!("Failed to make chat request: {}", error_msg)
-                }
-            } else {
-                format!("Failed to make chat request: {}", error_msg)
-            }
-        })?;
+    let activation_response: ActivationResponse = parse_json_response(response, "activation").await?;
     
     Ok(activation_response)
 }
@@ -250,53 +220,23 @@ pub fn mask_license_key_cmd(license_key: String) -> String {
 }
 
 #[tauri::command]
-pub async fn get_checkout_url() -> Result<CheckoutResponse, String> {
-    // Get payment endpoint and API access key from environment
-    let payment_endpoint = get_payment_endpoint()?;
-    let api_access_key = get_api_access_key()?;
+pub async fn get_checkout_url(app: AppHandle) -> Result<CheckoutResponse, ApiError> {
+    // Resolve the configured payment backend
+    let endpoints = endpoints::endpoints(&app);
+    let payment_endpoint = endpoints.payment_base()?;
+    endpoints.check_auth()?;
     
     // Make HTTP request to checkout endpoint with authorization header
-    let client = reqwest::Client::new();
+    let client = http::client(&app);
     let url = format!("{}/checkout", payment_endpoint);
     
-    let response = client
-        .post(&url)
-        .header("Content-Type", "application/json")
-        .header("Authorization", format!("Bearer {}", api_access_key))
+    let response = endpoints
+        .authorize(client.post(&url).header("Content-Type", "application/json"))
         .json(&serde_json::json!({}))
         .send()
         .await
-        .map_err(|e| {
-            let error_msg = format!("{}", e);
-            if error_msg.contains("url (") {
-                // Remove the URL part from the error message
-                let parts: Vec<&str> = error_msg.split(" for url (").collect();
-                if parts.len() > 1 {
-                    format!("Failed to make chat request: {}", parts[0])
-                } else {
-                    format!("Failed to make chat request: {}", error_msg)
-                }
-            } else {
-                format!("Failed to make chat request: {}", error_msg)
-            }
-        })?;
+        .map_err(|e| ApiError::from_reqwest("checkout", e))?;
     
-    let checkout_response: CheckoutResponse = response
-        .json()
-        .await
-        .map_err(|e| {
-            let error_msg = format!("{}", e);
-            if error_msg.contains("url (") {
-                // Remove the URL part from the error message
-                let parts: Vec<&str> = error_msg.split(" for url (").collect();
-                if parts.len() > 1 {
-                    format!("Failed to make chat request: {}", parts[0])
-                } else {
-                    format!("Failed to make chat request: {}", error_msg)
-                }
-            } else {
-                format!("Failed to make chat request: {}", error_msg)
-            }
-        })?;    
+    let checkout_response: CheckoutResponse = parse_json_response(response, "checkout").await?;
     Ok(checkout_response)
 }
\ No newline at end of file
@@ -1,39 +1,31 @@
 use serde::{Deserialize, Serialize};
-use std::env;
 use tauri::{AppHandle, Manager, Emitter};
-use futures_util::StreamExt;
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
-fn get_app_endpoint() -> Result<String, String> {
-    if let Ok(endpoint) = env::var("APP_ENDPOINT") {
-        return Ok(endpoint);
-    }
-    
-    match option_env!("APP_ENDPOINT") {
-        Some(endpoint) => Ok(endpoint.to_string()),
-        None => Err("APP_ENDPOINT environment variable not set. Please ensure it's set during the build process.".to_string())
-    }
-}
-
-fn get_api_access_key() -> Result<String, String> {
-    if let Ok(key) = env::var("API_ACCESS_KEY") {
-        return Ok(key);
-    }
-    
-    match option_env!("API_ACCESS_KEY") {
-        Some(key) => Ok(key.to_string()),
-        None => Err("API_ACCESS_KEY environment variable not set. Please ensure it's set during the build process.".to_string())
-    }
-}
+use crate::context;
+use crate::endpoints;
+use crate::error::ApiError;
+use crate::http;
+use crate::model_cache;
+use crate::retry;
+use crate::sse::SseStream;
+use crate::usage::{self, TokenUsage, UsageKind};
+use crate::providers::{
+    self, openai, ChatMessage, ChatOutput, ContentPart, DeltaSink, GenerationOptions, ReasoningOptions, Role, ToolCall,
+    ToolDefinition, ToolRoundOrigin,
+};
 
 // Secure storage functions
-fn get_secure_storage_path(app: &AppHandle) -> Result<PathBuf, String> {
+fn get_secure_storage_path(app: &AppHandle) -> Result<PathBuf, ApiError> {
     let app_data_dir = app.path().app_data_dir()
-        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+        .map_err(|e| ApiError::Storage(format!("Failed to get app data directory: {}", e)))?;
     
     fs::create_dir_all(&app_data_dir)
-        .map_err(|e| format!("Failed to create app data directory: {}", e))?;
+        .map_err(|e| ApiError::Storage(format!("Failed to create app data directory: {}", e)))?;
     
     Ok(app_data_dir.join("secure_storage.json"))
 }
@@ -45,21 +37,25 @@ struct SecureStorage {
     selected_extab_model: Option<String>,
 }
 
-async fn get_stored_credentials(app: &AppHandle) -> Result<(String, String, Option<Model>), String> {
+fn license_error(message: &str) -> ApiError {
+    ApiError::License { status: None, message: message.to_string() }
+}
+
+async fn get_stored_credentials(app: &AppHandle) -> Result<(String, String, Option<Model>), ApiError> {
     let storage_path = get_secure_storage_path(app)?;
     
     if !storage_path.exists() {
-        return Err("No license found. Please activate your license first.".to_string());
+        return Err(license_error("No license found. Please activate your license first."));
     }
     
     let content = fs::read_to_string(&storage_path)
-        .map_err(|e| format!("Failed to read storage file: {}", e))?;
+        .map_err(|e| ApiError::Storage(format!("Failed to read storage file: {}", e)))?;
     
     let storage: SecureStorage = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse storage file: {}", e))?;
+        .map_err(|e| ApiError::Storage(format!("Failed to parse storage file: {}", e)))?;
     
-    let license_key = storage.license_key.ok_or("License key not found".to_string())?;
-    let instance_id = storage.instance_id.ok_or("Instance ID not found".to_string())?;
+    let license_key = storage.license_key.ok_or_else(|| license_error("License key not found"))?;
+    let instance_id = storage.instance_id.ok_or_else(|| license_error("Instance ID not found"))?;
 
     let selected_model: Option<Model> = storage.selected_extab_model
         .and_then(|json_str| serde_json::from_str(&json_str).ok());
@@ -67,6 +63,16 @@ async fn get_stored_credentials(app: &AppHandle) -> Result<(String, String, Opti
     Ok((license_key, instance_id, selected_model))
 }
 
+/// The selected Extab model, without requiring a license.
+fn stored_selected_model(app: &AppHandle) -> Option<Model> {
+    get_secure_storage_path(app)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str::<SecureStorage>(&content).ok())
+        .and_then(|storage| storage.selected_extab_model)
+        .and_then(|json_str| serde_json::from_str(&json_str).ok())
+}
+
 // Audio API Structs
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AudioRequest {
@@ -78,15 +84,42 @@ pub struct AudioResponse {
     success: bool,
     transcription: Option<String>,
     error: Option<String>,
+    // Seconds of audio transcribed, when the backend reports it
+    #[serde(default, alias = "duration_seconds")]
+    duration: Option<f64>,
+    // OpenAI-style `{type: "duration", seconds}` or token counts
+    #[serde(default)]
+    usage: Option<serde_json::Value>,
 }
 
 // Chat API Structs
-#[derive(Debug, Serialize, Deserialize)]
+//
+// `tools` and `tool_messages` extend the Extab chat body for tool calling. The
+// backend has to forward them to the model as OpenAI `tools` and as messages
+// after the user turn, and stream tool call deltas back in OpenAI format. A
+// backend without that support ignores them and the model answers in text.
+// Both are omitted when unused, so plain chats send the original body.
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ChatRequest {
     user_message: String,
     system_prompt: Option<String>,
     image_base64: Option<serde_json::Value>, // Can be string or array
+    // JSON array of `{role, content: [parts]}`, oldest first, see `history_json`
     history: Option<String>,
+    // Function tools in OpenAI format
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<serde_json::Value>>,
+    // Assistant tool calls and tool results in OpenAI format, following the user message
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_messages: Option<Vec<serde_json::Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reasoning: Option<ReasoningOptions>,
+    // Sampling parameters as top-level OpenAI fields
+    #[serde(flatten)]
+    generation: GenerationOptions,
+    // Used instead of the selected model for this request, sent as headers
+    #[serde(skip)]
+    model_override: Option<Model>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -120,14 +153,18 @@ pub struct ModelsResponse {
 pub async fn transcribe_audio(
     app: AppHandle,
     audio_base64: String,
-) -> Result<AudioResponse, String> {
-    // Get environment variables
-    let app_endpoint = get_app_endpoint()?;
-    let api_access_key = get_api_access_key()?;
+) -> Result<AudioResponse, ApiError> {
+    // Resolve the configured backend
+    let endpoints = endpoints::endpoints(&app);
+    let app_endpoint = endpoints.audio_base()?;
+    endpoints.check_auth()?;
     
     // Get stored credentials
     let (license_key, instance_id, _) = get_stored_credentials(&app).await?;
     
+    let started = Instant::now();
+    let wav_seconds = usage::wav_duration_seconds(&audio_base64);
+    
     // Prepare audio request
     let audio_request = AudioRequest {
         audio_base64,
@@ -135,249 +172,501 @@ pub async fn transcribe_audio(
     };
     
     // Make HTTP request to audio endpoint
-    let client = reqwest::Client::new();
+    let client = http::client(&app);
     let url = format!("{}/api/audio", app_endpoint);
     
-    let response = client
-        .post(&url)
-        .header("Content-Type", "application/json")
-        .header("Authorization", format!("Bearer {}", api_access_key))
-        .header("license_key", &license_key)
-        .header("instance", &instance_id)
-        .json(&audio_request)
-        .send()
-        .await
-        .map_err(|e| {
-            let error_msg = format!("{}", e);
-            if error_msg.contains("url (") {
-                // Remove the URL part from the error message
-                let parts: Vec<&str> = error_msg.split(" for url (").collect();
-                if parts.len() > 1 {
-                    format!("Failed to make audio request: {}", parts[0])
-                } else {
-                    format!("Failed to make audio request: {}", error_msg)
-                }
-            } else {
-                format!("Failed to make audio request: {}", error_msg)
-            }
-        })?;
-    
-    // Check if the response is successful
-    if !response.status().is_success() {
-        let status = response.status();
-        let error_text = response.text().await.unwrap_or_else(|_| "Unknown server error".to_string());
-        
-        // Try to parse error as JSON to get a more specific error message
-        if let Ok(error_json) = serde_json::from_str::<serde_json::Value>(&error_text) {
-            if let Some(error_msg) = error_json.get("error").and_then(|e| e.as_str()) {
-                return Err(format!("Server error ({}): {}", status, error_msg));
-            } else if let Some(message) = error_json.get("message").and_then(|m| m.as_str()) {
-                return Err(format!("Server error ({}): {}", status, message));
-            }
-        }
-        
-        return Err(format!("Server error ({}): {}", status, error_text));
-    }
+    let response = retry::send_with_retry(&app, "audio", || {
+        endpoints
+            .authorize(client.post(&url).header("Content-Type", "application/json"))
+            .header("license_key", &license_key)
+            .header("instance", &instance_id)
+            .json(&audio_request)
+    })
+    .await?;
     
     let audio_response: AudioResponse = response
         .json()
         .await
-        .map_err(|e| format!("Failed to parse audio response: {}", e))?;
+        .map_err(|e| ApiError::Parse(format!("Failed to parse audio response: {}", e.without_url())))?;
+    
+    let reported = audio_response.usage.as_ref();
+    let count = |key: &str| reported.and_then(|u| u[key].as_u64()).unwrap_or_default();
+    let audio_seconds = audio_response
+        .duration
+        .or_else(|| reported.and_then(|u| u["seconds"].as_f64()))
+        .or(wav_seconds);
+    let token_usage = TokenUsage {
+        input_tokens: count("input_tokens"),
+        output_tokens: count("output_tokens"),
+        ..TokenUsage::default()
+    };
+    let model = reported.and_then(|u| u["model"].as_str()).unwrap_or("transcription");
+    let latency_ms = started.elapsed().as_millis() as u64;
+    usage::record(&app, UsageKind::Transcription, "extab", model, token_usage, audio_seconds, latency_ms);
     
     Ok(audio_response)
 }
 
+// Chat stream lifecycle events, tagged with the request id so overlapping
+// streams can be told apart. Deltas are emitted by `providers::DeltaSink`.
+#[derive(Debug, Serialize, Clone)]
+pub struct ChatStreamComplete {
+    request_id: String,
+    content: String,
+    reasoning: String,
+    tool_calls: Vec<ToolCall>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ChatStreamCancelled {
+    request_id: String,
+    // Text received before the stream was cancelled
+    content: String,
+}
+
+async fn run_chat_stream(
+    app: AppHandle,
+    chat_request: ChatRequest,
+    mut sink: DeltaSink,
+) -> Result<ChatOutput, ApiError> {
+    // Resolve the configured backend
+    let endpoints = endpoints::endpoints(&app);
+    let app_endpoint = endpoints.chat_base()?;
+    endpoints.check_auth()?;
+    
+    // Get stored credentials
+    let (license_key, instance_id, stored_model) = get_stored_credentials(&app).await?;
+    let selected_model = chat_request.model_override.clone().or(stored_model);
+    let (provider, model) = selected_model.as_ref().map_or((None, None), |m| (Some(m.provider.clone()), Some(m.model.clone())));
+    
+    // Make HTTP request to chat endpoint with streaming
+    let client = http::client(&app);
+    let url = format!("{}/api/chat?stream=true", app_endpoint);
+    
+    let provider = provider.unwrap_or("None".to_string());
+    let model = model.unwrap_or("None".to_string());
+    // Retries stop once the stream starts, partial answers are never replayed
+    let response = retry::send_with_retry(&app, "chat", || {
+        endpoints
+            .authorize(client.post(&url).header("Content-Type", "application/json"))
+            .header("license_key", &license_key)
+            .header("instance", &instance_id)
+            .header("provider", &provider)
+            .header("model", &model)
+            .json(&chat_request)
+    })
+    .await?;
+    
+    // Handle streaming response, the endpoint streams OpenAI-style chunks
+    let mut stream = SseStream::new(response.bytes_stream());
+    
+    while let Some(event) = stream.next_event().await? {
+        if let Some(chunk) = event.json() {
+            for delta in openai::parse_chunk(&chunk) {
+                sink.push(delta);
+            }
+        }
+    }
+    
+    let output = sink.finish()?;
+    // Without a selected model the endpoint picks one, and doesn't say which
+    let (provider, model) = match selected_model {
+        Some(_) => (provider.as_str(), model.as_str()),
+        None => ("extab", ""),
+    };
+    usage::record_chat(&app, provider, model, &output);
+    Ok(output)
+}
+
+/// Streams an Extab request as a cancellable task under `request_id`.
+async fn run_extab_stream(app: AppHandle, request_id: String, chat_request: ChatRequest) -> Result<ChatOutput, ApiError> {
+    let partial = Arc::new(Mutex::new(String::new()));
+    let hide_reasoning = chat_request.reasoning.as_ref().is_some_and(|r| r.hide);
+    let sink = DeltaSink::new(app.clone(), request_id.clone(), partial.clone()).hide_reasoning(hide_reasoning);
+    
+    let task = {
+        let app = app.clone();
+        let request_id = request_id.clone();
+        async move {
+            let output = run_chat_stream(app.clone(), chat_request.clone(), sink).await?;
+            providers::park_tool_round(&app, &request_id, ToolRoundOrigin::Extab { request: chat_request }, &output);
+            Ok(output)
+        }
+    };
+    
+    run_cancellable(&app, request_id, partial, task).await
+}
+
+/// Resends a parked Extab request with the tool round appended.
+pub(crate) async fn continue_extab_stream(
+    app: AppHandle,
+    request_id: String,
+    mut chat_request: ChatRequest,
+    assistant: &ChatMessage,
+    tool_results: &ChatMessage,
+) -> Result<ChatOutput, ApiError> {
+    let tool_messages = chat_request.tool_messages.get_or_insert_with(Vec::new);
+    tool_messages.extend(openai::message_json(assistant));
+    tool_messages.extend(openai::message_json(tool_results));
+    
+    run_extab_stream(app, request_id, chat_request).await
+}
+
+/// History in the shape the Extab endpoint expects, text and image parts in
+/// OpenAI format.
+fn history_json(history: &[ChatMessage]) -> String {
+    let messages: Vec<serde_json::Value> = history
+        .iter()
+        .flat_map(|message| {
+            let role = match message.role {
+                Role::System => "system",
+                Role::User => "user",
+                Role::Assistant => "assistant",
+                // Tool turns need the OpenAI tool message shape
+                Role::Tool => return openai::message_json(message),
+            };
+            if message.content.iter().any(|part| matches!(part, ContentPart::ToolCall(_))) {
+                return openai::message_json(message);
+            }
+
+            let content: Vec<serde_json::Value> = message
+                .content
+                .iter()
+                .filter_map(|part| match part {
+                    ContentPart::Text { text } => Some(serde_json::json!({ "type": "text", "text": text })),
+                    ContentPart::Image { .. } => part.image_data().map(|(mime, data)| {
+                        serde_json::json!({
+                            "type": "image_url",
+                            "image_url": { "url": format!("data:{};base64,{}", mime, data) },
+                        })
+                    }),
+                    _ => None,
+                })
+                .collect();
+            vec![serde_json::json!({ "role": role, "content": content })]
+        })
+        .collect();
+    serde_json::Value::Array(messages).to_string()
+}
+
+/// The current user turn as a message.
+pub(crate) fn user_turn(user_message: &str, image_base64: Option<&serde_json::Value>) -> ChatMessage {
+    let images = match image_base64 {
+        Some(serde_json::Value::Array(images)) => images.iter().filter_map(|i| i.as_str()).collect(),
+        Some(serde_json::Value::String(image)) => vec![image.as_str()],
+        _ => Vec::new(),
+    };
+
+    let mut content = vec![ContentPart::Text { text: user_message.to_string() }];
+    content.extend(images.into_iter().map(|data| ContentPart::Image { data: data.to_string(), mime_type: None }));
+    ChatMessage { role: Role::User, content }
+}
+
+/// Looks up a model override by id or model name in the Extab catalog.
+async fn resolve_model_override(app: &AppHandle, model: &str) -> Result<Model, ApiError> {
+    let catalog = fetch_models(app.clone(), None).await?;
+    let found = catalog
+        .models
+        .into_iter()
+        .find(|m| m.id == model || m.model == model)
+        .ok_or_else(|| ApiError::Config(format!("Unknown model: {}", model)))?;
+    
+    if !found.is_available {
+        return Err(ApiError::Config(format!("Model {} is currently unavailable", found.name)));
+    }
+    Ok(found)
+}
+
+/// One chat turn as `chat_stream` takes it, for commands built on the same
+/// request handling.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ChatInput {
+    pub user_message: String,
+    pub system_prompt: Option<String>,
+    pub image_base64: Option<serde_json::Value>,
+    // Oldest first
+    pub history: Vec<ChatMessage>,
+    pub tools: Vec<ToolDefinition>,
+    pub reasoning: Option<ReasoningOptions>,
+    pub generation: GenerationOptions,
+}
+
+/// Validates the options, resolves a model override and fits the history
+/// into the model's context window.
+async fn build_chat_request(app: &AppHandle, input: ChatInput) -> Result<ChatRequest, ApiError> {
+    let ChatInput { user_message, system_prompt, image_base64, history, tools, reasoning, generation } = input;
+    generation.validate()?;
+    
+    let model_override = match generation.model.as_deref() {
+        Some(model) => Some(resolve_model_override(app, model).await?),
+        None => None,
+    };
+    
+    // Trim older turns to fit the model's context window
+    let model = model_override.clone().or_else(|| stored_selected_model(app)).map(|m| m.model);
+    let fixed = context::message_tokens(&user_turn(&user_message, image_base64.as_ref()))
+        + system_prompt.as_deref().map_or(0, context::count_tokens);
+    let budget = context::history_budget(model.as_deref(), fixed, generation.max_tokens)?;
+    let history = (!history.is_empty()).then(|| history_json(&context::fit_history(history, budget)));
+    
+    Ok(ChatRequest {
+        user_message,
+        system_prompt,
+        image_base64,
+        history,
+        tools: (!tools.is_empty()).then(|| openai::tools_json(&tools)),
+        tool_messages: None,
+        reasoning,
+        generation,
+        model_override,
+    })
+}
+
+/// Builds an Extab request from `input` and streams it under `request_id`.
+pub(crate) async fn run_chat_input(app: AppHandle, request_id: String, input: ChatInput) -> Result<ChatOutput, ApiError> {
+    let chat_request = build_chat_request(&app, input).await?;
+    run_extab_stream(app, request_id, chat_request).await
+}
+
 // Chat API Command with Streaming
+// Events are tagged with `request_id`, see `providers::DeltaSink`. The caller
+// should pass its own id so it can cancel before the call returns. When the
+// answer ends in tool calls, continue with `submit_tool_results`. `options`
+// overrides the selected model and sampling parameters for this call only.
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn chat_stream(
     app: AppHandle,
     user_message: String,
     system_prompt: Option<String>,
     image_base64: Option<serde_json::Value>,
-    history: Option<String>,
-) -> Result<String, String> {
-    // Get environment variables
-    let app_endpoint = get_app_endpoint()?;
-    let api_access_key = get_api_access_key()?;
+    history: Option<Vec<ChatMessage>>,
+    tools: Option<Vec<ToolDefinition>>,
+    reasoning: Option<ReasoningOptions>,
+    options: Option<GenerationOptions>,
+    request_id: Option<String>,
+) -> Result<String, ApiError> {
+    let request_id = request_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
     
-    // Get stored credentials
-    let (license_key, instance_id, selected_model) = get_stored_credentials(&app).await?;
-    let (provider, model) = selected_model.as_ref().map_or((None, None), |m| (Some(m.provider.clone()), Some(m.model.clone())));
-   
     // Prepare chat request
-    let chat_request = ChatRequest {
+    let input = ChatInput {
         user_message,
         system_prompt,
         image_base64,
-        history
+        history: history.unwrap_or_default(),
+        tools: tools.unwrap_or_default(),
+        reasoning,
+        generation: options.unwrap_or_default(),
     };
     
-    // Make HTTP request to chat endpoint with streaming
-    let client = reqwest::Client::new();
-    let url = format!("{}/api/chat?stream=true", app_endpoint);
-    
-    let response = client
-        .post(&url)
-        .header("Content-Type", "application/json")
-        .header("Authorization", format!("Bearer {}", api_access_key))
-        .header("license_key", &license_key)
-        .header("instance", &instance_id)
-        .header("provider", &provider.unwrap_or("None".to_string()))
-        .header("model", &model.unwrap_or("None".to_string()))
-        .json(&chat_request)
-        .send()
-        .await
-        .map_err(|e| {
-            let error_msg = format!("{}", e);
-            if error_msg.contains("url (") {
-                // Remove the URL part from the error message
-                let parts: Vec<&str> = error_msg.split(" for url (").collect();
-                if parts.len() > 1 {
-                    format!("Failed to make chat request: {}", parts[0])
-                } else {
-                    format!("Failed to make chat request: {}", error_msg)
-                }
-            } else {
-                format!("Failed to make chat request: {}", error_msg)
-            }
-        })?;
-    
-    // Check if the response is successful
-    if !response.status().is_success() {
-        let status = response.status();
-        let error_text = response.text().await.unwrap_or_else(|_| "Unknown server error".to_string());
-        
-        // Try to parse error as JSON to get a more specific error message
-        if let Ok(error_json) = serde_json::from_str::<serde_json::Value>(&error_text) {
-            if let Some(error_msg) = error_json.get("error").and_then(|e| e.as_str()) {
-                return Err(format!("Server error ({}): {}", status, error_msg));
-            } else if let Some(message) = error_json.get("message").and_then(|m| m.as_str()) {
-                return Err(format!("Server error ({}): {}", status, message));
-            }
+    let output = run_chat_input(app, request_id, input).await?;
+    Ok(output.content)
+}
+
+// Unregisters a stream, aborting it if the caller went away first, e.g. a
+// cancelled comparison dropping its answers
+struct StreamRegistration {
+    streams: Arc<Mutex<HashMap<String, tokio::task::AbortHandle>>>,
+    request_id: String,
+}
+
+impl Drop for StreamRegistration {
+    fn drop(&mut self) {
+        if let Some(handle) = self.streams.lock().unwrap().remove(&self.request_id) {
+            handle.abort();
         }
-        
-        return Err(format!("Server error ({}): {}", status, error_text));
     }
+}
+
+/// Runs a chat stream as its own task, registered under `request_id` so
+/// `cancel_chat_stream` can abort it. Aborting drops the response body
+/// mid-read, which closes the connection. Emits the complete or cancelled
+/// event, `partial` holds the text streamed so far.
+pub(crate) async fn run_cancellable<F>(
+    app: &AppHandle,
+    request_id: String,
+    partial: Arc<Mutex<String>>,
+    stream: F,
+) -> Result<ChatOutput, ApiError>
+where
+    F: std::future::Future<Output = Result<ChatOutput, ApiError>> + Send + 'static,
+{
+    let streams = app.state::<crate::ChatStreamState>().streams.clone();
     
-    // Handle streaming response
-    let mut stream = response.bytes_stream();
-    let mut full_response = String::new();
-    let mut buffer = String::new();
-    
-    while let Some(chunk) = stream.next().await {
-        match chunk {
-            Ok(bytes) => {
-                let chunk_str = String::from_utf8_lossy(&bytes);
-                buffer.push_str(&chunk_str);
-                
-                // Process complete lines
-                let lines: Vec<&str> = buffer.split('\n').collect();
-                let incomplete_line = lines.last().unwrap_or(&"").to_string();
-                
-                for line in &lines[..lines.len()-1] { // Process all but the last (potentially incomplete) line
-                    let trimmed_line = line.trim();
-                    
-                    if trimmed_line.starts_with("data: ") {
-                        let json_str = trimmed_line.strip_prefix("data: ").unwrap_or("");
-                        
-                        if json_str == "[DONE]" {
-                            break;
-                        }
-                        
-                        if !json_str.is_empty() {
-                            // Try to parse the JSON and extract content
-                            if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str) {
-                                if let Some(choices) = parsed.get("choices").and_then(|c| c.as_array()) {
-                                    if let Some(first_choice) = choices.first() {
-                                        if let Some(delta) = first_choice.get("delta") {
-                                            if let Some(content) = delta.get("content").and_then(|c| c.as_str()) {
-                                                full_response.push_str(content);
-                                                // Emit just the content to frontend
-                                                let _ = app.emit("chat_stream_chunk", content);
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-                
-                // Update buffer with incomplete line
-                buffer = incomplete_line;
-            }
-            Err(e) => {
-                return Err(format!("Stream error: {}", e));
-            }
+    let (task, registration) = {
+        let mut locked = streams.lock().unwrap();
+        if locked.contains_key(&request_id) {
+            return Err(ApiError::Config(format!("Chat stream {} is already running", request_id)));
         }
+        let task = tokio::spawn(stream);
+        locked.insert(request_id.clone(), task.abort_handle());
+        (task, StreamRegistration { streams: streams.clone(), request_id: request_id.clone() })
+    };
+    
+    let result = task.await;
+    drop(registration);
+    
+    match result {
+        Ok(Ok(output)) => {
+            // Emit completion event
+            let _ = app.emit("chat_stream_complete", ChatStreamComplete {
+                request_id,
+                content: output.content.clone(),
+                reasoning: output.reasoning.clone(),
+                tool_calls: output.tool_calls.clone(),
+            });
+            Ok(output)
+        }
+        Ok(Err(e)) => Err(e),
+        Err(e) if e.is_cancelled() => {
+            let content = partial.lock().unwrap().clone();
+            let _ = app.emit("chat_stream_cancelled", ChatStreamCancelled {
+                request_id: request_id.clone(),
+                content,
+            });
+            Err(ApiError::Cancelled(format!("Chat stream {} was cancelled", request_id)))
+        }
+        Err(e) => Err(ApiError::Network(format!("Chat stream task failed: {}", e))),
+    }
+}
+
+/// Stops a running chat stream, or every answer of a model comparison when
+/// given the comparison's id, and drops any tool round waiting on results.
+/// Returns false if there was nothing to cancel.
+#[tauri::command]
+pub async fn cancel_chat_stream(app: AppHandle, request_id: String) -> Result<bool, ApiError> {
+    let state = app.state::<crate::ChatStreamState>();
+    // Comparison answers run as "<request_id>:<index>", structured output
+    // repairs as "<request_id>:repair-<n>"
+    let prefix = format!("{}:", request_id);
+    let handles: Vec<_> = {
+        let mut streams = state.streams.lock().unwrap();
+        let ids: Vec<String> = streams
+            .keys()
+            .filter(|id| **id == request_id || id.starts_with(&prefix))
+            .cloned()
+            .collect();
+        ids.iter().filter_map(|id| streams.remove(id)).collect()
+    };
+    
+    for handle in &handles {
+        handle.abort();
     }
     
-    // Emit completion event
-    let _ = app.emit("chat_stream_complete", &full_response);
+    // Nobody will submit results for a cancelled conversation
+    let mut rounds = state.tool_rounds.lock().unwrap();
+    let parked = rounds.len();
+    rounds.retain(|id, _| *id != request_id && !id.starts_with(&prefix));
     
-    Ok(full_response)
+    Ok(!handles.is_empty() || rounds.len() < parked)
+}
+
+// Returned by fetch_models
+#[derive(Debug, Serialize, Clone)]
+pub struct ModelCatalog {
+    models: Vec<Model>,
+    // Served from the cache because the backend couldn't be reached
+    stale: bool,
+    // Unix seconds when the backend last confirmed the list
+    fetched_at: i64,
+}
+
+impl ModelCatalog {
+    fn new(cached: model_cache::CachedModels, stale: bool) -> Self {
+        ModelCatalog { models: cached.models, stale, fetched_at: cached.fetched_at }
+    }
+}
+
+// Emitted as `selected_model_unavailable`
+#[derive(Debug, Serialize, Clone)]
+pub struct SelectedModelUnavailable {
+    model: Model,
+    // "removed" when it's no longer listed, "unavailable" when it's listed but disabled
+    reason: &'static str,
+}
+
+/// Emits `selected_model_unavailable` if the stored Extab model is gone from
+/// the catalog or no longer available.
+fn check_selected_model(app: &AppHandle, models: &[Model]) {
+    let Some(selected) = stored_selected_model(app) else {
+        return;
+    };
+
+    let reason = match models.iter().find(|m| m.id == selected.id) {
+        None => "removed",
+        Some(model) if !model.is_available => "unavailable",
+        Some(_) => return,
+    };
+    let event = SelectedModelUnavailable { model: selected, reason };
+    let _ = app
+        .emit("selected_model_unavailable", event)
+        .map_err(|e| eprintln!("emit selected_model_unavailable failed: {}", e));
 }
 
 // Models API Command
+// Serves the cached catalog within its TTL, otherwise revalidates it with
+// If-None-Match. Falls back to the cache, marked stale, when the backend
+// can't be reached.
 #[tauri::command]
-pub async fn fetch_models() -> Result<Vec<Model>, String> {
-    // Get environment variables
-    let app_endpoint = get_app_endpoint()?;
-    let api_access_key = get_api_access_key()?;
+pub async fn fetch_models(app: AppHandle, force_refresh: Option<bool>) -> Result<ModelCatalog, ApiError> {
+    let cached = model_cache::load(&app);
+    if let Some(cached) = cached.as_ref().filter(|c| c.is_fresh() && !force_refresh.unwrap_or(false)) {
+        check_selected_model(&app, &cached.models);
+        return Ok(ModelCatalog::new(cached.clone(), false));
+    }
+
+    // Resolve the configured backend
+    let endpoints = endpoints::endpoints(&app);
+    let app_endpoint = endpoints.models_base()?;
+    endpoints.check_auth()?;
     
     // Make HTTP request to models endpoint
-    let client = reqwest::Client::new();
+    let client = http::client(&app);
     let url = format!("{}/api/models", app_endpoint);
+    let etag = cached.as_ref().and_then(|c| c.etag.clone());
     
-    let response = client
-        .post(&url)
-        .header("Content-Type", "application/json")
-        .header("Authorization", format!("Bearer {}", api_access_key))
-        .send()
-        .await
-        .map_err(|e| {
-            let error_msg = format!("{}", e);
-            if error_msg.contains("url (") {
-                // Remove the URL part from the error message
-                let parts: Vec<&str> = error_msg.split(" for url (").collect();
-                if parts.len() > 1 {
-                    format!("Failed to make models request: {}", parts[0])
-                } else {
-                    format!("Failed to make models request: {}", error_msg)
-                }
-            } else {
-                format!("Failed to make models request: {}", error_msg)
-            }
-        })?;
-        
-    // Check if the response is successful
-    if !response.status().is_success() {
-        let status = response.status();
-        let error_text = response.text().await.unwrap_or_else(|_| "Unknown server error".to_string());
-        
-        // Try to parse error as JSON to get a more specific error message
-        if let Ok(error_json) = serde_json::from_str::<serde_json::Value>(&error_text) {
-            if let Some(error_msg) = error_json.get("error").and_then(|e| e.as_str()) {
-                return Err(format!("Server error ({}): {}", status, error_msg));
-            } else if let Some(message) = error_json.get("message").and_then(|m| m.as_str()) {
-                return Err(format!("Server error ({}): {}", status, message));
-            }
+    let result = retry::send_with_retry(&app, "models", || {
+        let builder = endpoints
+            .authorize(client.post(&url).header("Content-Type", "application/json"));
+        match &etag {
+            Some(etag) => builder.header(reqwest::header::IF_NONE_MATCH, etag),
+            None => builder,
         }
-        
-        return Err(format!("Server error ({}): {}", status, error_text));
-    }
+    })
+    .await;
     
+    let response = match (result, cached) {
+        (Ok(response), Some(mut cached)) if response.status() == reqwest::StatusCode::NOT_MODIFIED => {
+            cached.touch();
+            model_cache::save(&app, &cached);
+            check_selected_model(&app, &cached.models);
+            return Ok(ModelCatalog::new(cached, false));
+        }
+        (Ok(response), _) => response,
+        // Offline or the backend is down, an old list beats none
+        (Err(error), Some(cached)) if error.is_retryable() => return Ok(ModelCatalog::new(cached, true)),
+        (Err(error), _) => return Err(error),
+    };
+    
+    let etag = response
+        .headers()
+        .get(reqwest::header::ETAG)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string());
     let models_response: ModelsResponse = response
         .json()
         .await
-        .map_err(|e| format!("Failed to parse models response: {}", e))?;
-        
-    Ok(models_response.models)
+        .map_err(|e| ApiError::Parse(format!("Failed to parse models response: {}", e.without_url())))?;
+    
+    let cached = model_cache::CachedModels::new(models_response.models, etag);
+    model_cache::save(&app, &cached);
+    check_selected_model(&app, &cached.models);
+    Ok(ModelCatalog::new(cached, false))
 }
 
 // Helper command to check if license is available
 #[tauri::command]
-pub async fn check_license_status(app: AppHandle) -> Result<bool, String> {
+pub async fn check_license_status(app: AppHandle) -> Result<bool, ApiError> {
     match get_stored_credentials(&app).await {
         Ok(_) => Ok(true),
         Err(_) => Ok(false),
@@ -0,0 +1,287 @@
+// Screenshot encoding: output format, byte budget and dimension limits
+
+use base64::Engine;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::{CompressionType, FilterType as PngFilterType, PngEncoder};
+use image::codecs::webp::WebPEncoder;
+use image::{imageops, DynamicImage, ImageEncoder, RgbImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+// Each fallback resize keeps 80% of the previous dimensions
+const DOWNSCALE_STEP: f64 = 0.8;
+const MAX_DOWNSCALE_STEPS: u32 = 20;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    #[default]
+    Jpeg,
+    Png,
+    Webp,
+}
+
+impl ImageFormat {
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ImageFormat::Jpeg => "image/jpeg",
+            ImageFormat::Png => "image/png",
+            ImageFormat::Webp => "image/webp",
+        }
+    }
+}
+
+/// How a capture should be encoded before it is handed to the frontend.
+/// The defaults match the previous behaviour: JPEG under 5MB at full size.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct EncodeOptions {
+    pub format: ImageFormat,
+    // Budget for the encoded bytes (before base64)
+    pub max_bytes: Option<usize>,
+    // Longest side in pixels
+    pub max_long_edge: Option<u32>,
+    // Total pixel count in megapixels
+    pub target_megapixels: Option<f64>,
+    // JPEG quality search range. PNG and WebP are lossless and ignore these.
+    pub min_quality: u8,
+    pub max_quality: u8,
+}
+
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        EncodeOptions {
+            format: ImageFormat::Jpeg,
+            max_bytes: Some(5_000_000),
+            max_long_edge: None,
+            target_megapixels: None,
+            min_quality: 35,
+            max_quality: 85,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EncodedImage {
+    pub image_base64: String,
+    pub format: ImageFormat,
+    pub mime_type: String,
+    // Chosen JPEG quality, None for lossless formats
+    pub quality: Option<u8>,
+    pub width: u32,
+    pub height: u32,
+    pub byte_size: usize,
+    // Output pixels per source pixel, 1.0 when the image wasn't resized
+    pub scale: f64,
+}
+
+/// Scale that satisfies the long-edge and megapixel limits, never above 1.0.
+fn dimension_scale(width: u32, height: u32, options: &EncodeOptions) -> f64 {
+    let mut scale = 1.0_f64;
+
+    if let Some(max_long_edge) = options.max_long_edge.filter(|e| *e > 0) {
+        let long_edge = width.max(height).max(1) as f64;
+        scale = scale.min(max_long_edge as f64 / long_edge);
+    }
+
+    if let Some(megapixels) = options.target_megapixels.filter(|mp| *mp > 0.0) {
+        let pixels = (width as f64 * height as f64).max(1.0);
+        scale = scale.min((megapixels * 1_000_000.0 / pixels).sqrt());
+    }
+
+    scale
+}
+
+fn resize(image: &RgbaImage, scale: f64) -> RgbaImage {
+    if scale >= 1.0 {
+        return image.clone();
+    }
+
+    let width = ((image.width() as f64 * scale).round() as u32).max(1);
+    let height = ((image.height() as f64 * scale).round() as u32).max(1);
+    imageops::resize(image, width, height, imageops::FilterType::Lanczos3)
+}
+
+/// Fast downscale until the longer side is at most `max_long_edge`, keeping
+/// the aspect ratio. For previews and buffered frames rather than output.
+pub fn thumbnail(image: &RgbaImage, max_long_edge: u32) -> RgbaImage {
+    let long_edge = image.width().max(image.height());
+    if long_edge <= max_long_edge {
+        return image.clone();
+    }
+
+    let scale = max_long_edge as f64 / long_edge as f64;
+    let width = ((image.width() as f64 * scale).round() as u32).max(1);
+    let height = ((image.height() as f64 * scale).round() as u32).max(1);
+    imageops::thumbnail(image, width, height)
+}
+
+fn encode_jpeg(rgb_image: &RgbImage, quality: u8) -> Result<Vec<u8>, String> {
+    let mut buffer = Vec::new();
+    JpegEncoder::new_with_quality(&mut buffer, quality)
+        .write_image(
+            rgb_image.as_raw(),
+            rgb_image.width(),
+            rgb_image.height(),
+            image::ExtendedColorType::Rgb8,
+        )
+        .map_err(|e| format!("Failed to encode to JPEG: {}", e))?;
+    Ok(buffer)
+}
+
+fn encode_lossless(image: &RgbaImage, format: ImageFormat) -> Result<Vec<u8>, String> {
+    let mut buffer = Vec::new();
+    let result = match format {
+        ImageFormat::Png => PngEncoder::new_with_quality(&mut buffer, CompressionType::Best, PngFilterType::Adaptive)
+            .write_image(image.as_raw(), image.width(), image.height(), image::ExtendedColorType::Rgba8),
+        ImageFormat::Webp => WebPEncoder::new_lossless(&mut buffer)
+            .write_image(image.as_raw(), image.width(), image.height(), image::ExtendedColorType::Rgba8),
+        ImageFormat::Jpeg => return Err("JPEG is not a lossless format".to_string()),
+    };
+    result.map_err(|e| format!("Failed to encode to {:?}: {}", format, e))?;
+    Ok(buffer)
+}
+
+/// Binary searches for the highest JPEG quality that fits the budget.
+/// Returns None when even the minimum quality is too large.
+fn encode_jpeg_within(image: &RgbaImage, options: &EncodeOptions) -> Result<Option<(Vec<u8>, u8)>, String> {
+    let min_quality = options.min_quality.clamp(1, 100);
+    let max_quality = options.max_quality.clamp(min_quality, 100);
+
+    // JPEG doesn't support alpha, convert once for every quality tried
+    let rgb_image = DynamicImage::ImageRgba8(image.clone()).to_rgb8();
+
+    let max_bytes = match options.max_bytes {
+        Some(max_bytes) => max_bytes,
+        None => return Ok(Some((encode_jpeg(&rgb_image, max_quality)?, max_quality))),
+    };
+
+    let mut best = None;
+    let (mut low, mut high) = (min_quality, max_quality);
+    while low <= high {
+        let quality = low + (high - low) / 2;
+        let buffer = encode_jpeg(&rgb_image, quality)?;
+
+        if buffer.len() <= max_bytes {
+            best = Some((buffer, quality));
+            low = quality + 1;
+        } else if quality == min_quality {
+            break;
+        } else {
+            high = quality - 1;
+        }
+    }
+
+    Ok(best)
+}
+
+/// Encodes an image according to `options`, downscaling further if the byte
+/// budget can't be met at the configured dimensions.
+pub fn encode_image(image: &RgbaImage, options: &EncodeOptions) -> Result<EncodedImage, String> {
+    let source_width = image.width().max(1);
+    let mut scale = dimension_scale(image.width(), image.height(), options);
+
+    for _ in 0..=MAX_DOWNSCALE_STEPS {
+        let resized = resize(image, scale);
+
+        let encoded = match options.format {
+            ImageFormat::Jpeg => encode_jpeg_within(&resized, options)?
+                .map(|(buffer, quality)| (buffer, Some(quality))),
+            format => {
+                let buffer = encode_lossless(&resized, format)?;
+                match options.max_bytes {
+                    Some(max_bytes) if buffer.len() > max_bytes => None,
+                    _ => Some((buffer, None)),
+                }
+            }
+        };
+
+        if let Some((buffer, quality)) = encoded {
+            return Ok(EncodedImage {
+                byte_size: buffer.len(),
+                image_base64: base64::engine::general_purpose::STANDARD.encode(buffer),
+                format: options.format,
+                mime_type: options.format.mime_type().to_string(),
+                quality,
+                width: resized.width(),
+                height: resized.height(),
+                scale: resized.width() as f64 / source_width as f64,
+            });
+        }
+
+        scale *= DOWNSCALE_STEP;
+    }
+
+    Err("Failed to fit image within the byte budget".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thumbnail_keeps_aspect_ratio() {
+        let wide = thumbnail(&RgbaImage::new(1920, 1080), 960);
+        assert_eq!((wide.width(), wide.height()), (960, 540));
+        let tall = thumbnail(&RgbaImage::new(100, 4000), 320);
+        assert_eq!((tall.width(), tall.height()), (8, 320));
+        // Never upscaled
+        let small = thumbnail(&RgbaImage::new(200, 100), 320);
+        assert_eq!((small.width(), small.height()), (200, 100));
+    }
+
+    // Noise doesn't compress, so the encoded size follows the quality
+    fn noise(width: u32, height: u32) -> RgbaImage {
+        let mut seed = 0x2545_f491_u32;
+        RgbaImage::from_fn(width, height, |_, _| {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            let [r, g, b, _] = seed.to_le_bytes();
+            image::Rgba([r, g, b, 255])
+        })
+    }
+
+    fn jpeg_options(max_bytes: Option<usize>) -> EncodeOptions {
+        EncodeOptions { max_bytes, ..EncodeOptions::default() }
+    }
+
+    fn jpeg_size(image: &RgbaImage, quality: u8) -> usize {
+        encode_jpeg(&DynamicImage::ImageRgba8(image.clone()).to_rgb8(), quality).unwrap().len()
+    }
+
+    #[test]
+    fn jpeg_search_picks_highest_quality_within_budget() {
+        let image = noise(64, 64);
+        let budget = jpeg_size(&image, 60);
+
+        let (buffer, quality) = encode_jpeg_within(&image, &jpeg_options(Some(budget))).unwrap().unwrap();
+        assert!(buffer.len() <= budget);
+        assert!(quality >= 60);
+        assert!(quality == 85 || jpeg_size(&image, quality + 1) > budget);
+    }
+
+    #[test]
+    fn jpeg_search_misses_below_min_quality() {
+        let image = noise(64, 64);
+        let budget = jpeg_size(&image, 35) - 1;
+
+        assert!(encode_jpeg_within(&image, &jpeg_options(Some(budget))).unwrap().is_none());
+    }
+
+    #[test]
+    fn jpeg_without_budget_uses_max_quality() {
+        let image = noise(16, 16);
+        let (_, quality) = encode_jpeg_within(&image, &jpeg_options(None)).unwrap().unwrap();
+        assert_eq!(quality, 85);
+    }
+
+    #[test]
+    fn encode_image_downscales_when_budget_is_missed() {
+        let image = noise(128, 128);
+        let budget = jpeg_size(&image, 35) / 2;
+
+        let encoded = encode_image(&image, &jpeg_options(Some(budget))).unwrap();
+        assert!(encoded.byte_size <= budget);
+        assert!(encoded.width < 128 && encoded.scale < 1.0);
+    }
+}
@@ -0,0 +1,286 @@
+// Screen capture commands built on xcap
+
+mod encode;
+mod monitor;
+mod recorder;
+mod redact;
+mod scale;
+mod store;
+mod watcher;
+mod window;
+
+pub use encode::{EncodeOptions, EncodedImage};
+pub use monitor::{MonitorInfo, MonitorTarget};
+pub use recorder::{export_screen_recording, start_screen_recording, stop_screen_recording, FrameBuffer};
+pub use redact::{load_settings as load_redaction_settings, RedactedRegion, RedactionOptions, RedactionState};
+pub use scale::ModelResolution;
+pub use store::{delete_capture, evict_captures, get_capture, list_captures, CaptureSource};
+pub use watcher::{start_screen_watch, stop_screen_watch};
+pub use window::WindowInfo;
+
+// Wrappers generated by #[tauri::command], generate_handler! looks them up
+// next to the command
+pub use recorder::{__cmd__export_screen_recording, __cmd__start_screen_recording, __cmd__stop_screen_recording};
+pub use store::{__cmd__delete_capture, __cmd__evict_captures, __cmd__get_capture, __cmd__list_captures};
+pub use watcher::{__cmd__start_screen_watch, __cmd__stop_screen_watch};
+
+use image::{imageops, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+use crate::computer_use::CoordinateScale;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RegionCapture {
+    pub image: EncodedImage,
+    // Crop origin and size in captured image pixels
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    // Image pixels per desktop unit, to map the crop back to screen coordinates
+    pub scale: f64,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Captures a monitor target with the saved redaction settings applied.
+/// Every capture that leaves this module, stored or streamed, starts here.
+fn capture_frame(app: &AppHandle, target: &MonitorTarget) -> Result<monitor::CapturedFrame, String> {
+    let mut frame = monitor::capture_target_frame(app, target)?;
+    redact::redact_configured(app, &mut frame)?;
+    Ok(frame)
+}
+
+// History is best effort, a failed save shouldn't lose the capture
+fn save_to_history(
+    app: &AppHandle,
+    save_as: Option<CaptureSource>,
+    image: &EncodedImage,
+    source_image: &RgbaImage,
+    monitor: Option<MonitorInfo>,
+) {
+    if let Some(source) = save_as {
+        if let Err(e) = store::save_capture(app, image, source_image, source, monitor) {
+            eprintln!("Failed to save capture: {}", e);
+        }
+    }
+}
+
+// Saved redaction may run OCR for seconds, so capture commands do their work
+// on the blocking pool instead of the main thread
+async fn run_blocking<T, F>(name: &str, work: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, String> + Send + 'static,
+{
+    tokio::task::spawn_blocking(work)
+        .await
+        .map_err(|e| format!("{} task failed: {}", name, e))?
+}
+
+/// Redacted capture of a monitor target, for other modules that post-process pixels.
+pub fn capture_image(app: &AppHandle, target: &MonitorTarget) -> Result<RgbaImage, String> {
+    capture_frame(app, target).map(|frame| frame.image)
+}
+
+#[tauri::command]
+pub fn list_monitors() -> Result<Vec<MonitorInfo>, String> {
+    let monitors = monitor::all_monitors()?;
+    Ok(monitors.iter().map(MonitorInfo::from).collect())
+}
+
+#[tauri::command]
+pub fn list_windows(app: AppHandle) -> Result<Vec<WindowInfo>, String> {
+    let windows = window::other_windows(&app)?;
+    Ok(windows.iter().map(WindowInfo::from).collect())
+}
+
+#[tauri::command]
+pub async fn capture_window(
+    app: AppHandle,
+    window_id: u32,
+    options: Option<EncodeOptions>,
+    save_as: Option<CaptureSource>,
+) -> Result<EncodedImage, String> {
+    run_blocking("Window capture", move || {
+        let mut frame = window::capture_window_by_id(&app, window_id)?;
+        redact::redact_configured(&app, &mut frame)?;
+        let image = encode::encode_image(&frame.image, &options.unwrap_or_default())?;
+
+        save_to_history(&app, save_as, &image, &frame.image, None);
+        Ok(image)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn capture_screenshot(
+    app: AppHandle,
+    target: Option<MonitorTarget>,
+    options: Option<EncodeOptions>,
+    save_as: Option<CaptureSource>,
+) -> Result<EncodedImage, String> {
+    let target = target.unwrap_or_default();
+    run_blocking("Screenshot", move || {
+        let frame = capture_frame(&app, &target)?;
+        let image = encode::encode_image(&frame.image, &options.unwrap_or_default())?;
+
+        save_to_history(&app, save_as, &image, &frame.image, frame.monitor);
+        Ok(image)
+    })
+    .await
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ModelCapture {
+    pub image: EncodedImage,
+    // Pass to the computer_use commands to map model coordinates to the screen
+    pub coordinate_scale: CoordinateScale,
+}
+
+/// Captures a screenshot sized for a vision model, along with the factors
+/// that map coordinates in the returned image back to screen coordinates.
+#[tauri::command]
+pub async fn capture_for_model(
+    app: AppHandle,
+    target: Option<MonitorTarget>,
+    resolution: Option<ModelResolution>,
+    options: Option<EncodeOptions>,
+    save_as: Option<CaptureSource>,
+) -> Result<ModelCapture, String> {
+    let target = target.unwrap_or_default();
+    run_blocking("Model capture", move || {
+        let frame = capture_frame(&app, &target)?;
+        let (desktop_width, desktop_height) = frame.desktop_size();
+
+        let resized = scale::downscale_for_model(&frame.image, &resolution.unwrap_or_default());
+        let image = encode::encode_image(&resized, &options.unwrap_or_default())?;
+
+        // Use the encoded size, the byte budget may have shrunk the image further
+        let coordinate_scale = scale::coordinate_scale(
+            image.width,
+            image.height,
+            desktop_width,
+            desktop_height,
+            frame.x,
+            frame.y,
+        );
+
+        save_to_history(&app, save_as, &image, &resized, frame.monitor);
+        Ok(ModelCapture {
+            image,
+            coordinate_scale,
+        })
+    })
+    .await
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RedactedCapture {
+    pub image: EncodedImage,
+    // Regions in captured image pixels, before any encoding resize
+    pub regions: Vec<RedactedRegion>,
+}
+
+/// Captures a screenshot and redacts sensitive regions before it is encoded,
+/// `redaction` merged into the saved settings so OCR runs once. Its style
+/// and OCR language win over the saved ones.
+#[tauri::command]
+pub async fn capture_redacted(
+    app: AppHandle,
+    target: Option<MonitorTarget>,
+    redaction: RedactionOptions,
+    options: Option<EncodeOptions>,
+    save_as: Option<CaptureSource>,
+) -> Result<RedactedCapture, String> {
+    let target = target.unwrap_or_default();
+    run_blocking("Redacted capture", move || {
+        let mut frame = monitor::capture_target_frame(&app, &target)?;
+        let redaction = app.state::<RedactionState>().settings().merged(redaction);
+        let regions = redact::redact_frame(&app, &mut frame, &redaction)?;
+        let image = encode::encode_image(&frame.image, &options.unwrap_or_default())?;
+
+        save_to_history(&app, save_as, &image, &frame.image, frame.monitor);
+        Ok(RedactedCapture { image, regions })
+    })
+    .await
+}
+
+#[tauri::command]
+pub fn get_redaction_settings(app: AppHandle) -> RedactionOptions {
+    app.state::<RedactionState>().settings()
+}
+
+/// Saves the redaction applied to every capture. Patterns are checked here
+/// so a bad one can't fail later captures.
+#[tauri::command]
+pub fn set_redaction_settings(app: AppHandle, settings: RedactionOptions) -> Result<(), String> {
+    redact::save_settings(&app, settings)
+}
+
+// Kept for callers that only need the base64 payload
+#[tauri::command]
+pub async fn capture_to_base64(
+    app: AppHandle,
+    target: Option<MonitorTarget>,
+    options: Option<EncodeOptions>,
+    save_as: Option<CaptureSource>,
+) -> Result<String, String> {
+    capture_screenshot(app, target, options, save_as).await.map(|encoded| encoded.image_base64)
+}
+
+/// Captures a region given in desktop units relative to the target's top-left
+/// corner. The crop happens before encoding so only the selection crosses IPC.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn capture_region(
+    app: AppHandle,
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+    monitor: Option<MonitorTarget>,
+    options: Option<EncodeOptions>,
+    save_as: Option<CaptureSource>,
+) -> Result<RegionCapture, String> {
+    if width <= 0.0 || height <= 0.0 {
+        return Err("Region width and height must be positive".to_string());
+    }
+
+    let target = monitor.unwrap_or_default();
+    run_blocking("Region capture", move || {
+        let frame = capture_frame(&app, &target)?;
+        let (captured_image, scale, source_monitor) = (frame.image, frame.scale, frame.monitor);
+
+        // Convert to image pixels and clamp to the captured area
+        let left = (x * scale).round().max(0.0) as u32;
+        let top = (y * scale).round().max(0.0) as u32;
+        let right = (((x + width) * scale).round().max(0.0) as u32).min(captured_image.width());
+        let bottom = (((y + height) * scale).round().max(0.0) as u32).min(captured_image.height());
+
+        if left >= right || top >= bottom {
+            return Err("Region is outside the captured area".to_string());
+        }
+
+        let cropped = imageops::crop_imm(&captured_image, left, top, right - left, bottom - top).to_image();
+        let image = encode::encode_image(&cropped, &options.unwrap_or_default())?;
+
+        save_to_history(&app, save_as, &image, &cropped, source_monitor);
+        Ok(RegionCapture {
+            image,
+            x: left,
+            y: top,
+            width: right - left,
+            height: bottom - top,
+            scale,
+        })
+    })
+    .await
+}
@@ -0,0 +1,180 @@
+// Monitor discovery and selection for screen capture
+
+use image::{imageops, RgbaImage};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use xcap::Monitor;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MonitorInfo {
+    pub id: u32,
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f32,
+    pub is_primary: bool,
+}
+
+impl From<&Monitor> for MonitorInfo {
+    fn from(monitor: &Monitor) -> Self {
+        MonitorInfo {
+            id: monitor.id(),
+            name: monitor.name().to_string(),
+            x: monitor.x(),
+            y: monitor.y(),
+            width: monitor.width(),
+            height: monitor.height(),
+            scale_factor: monitor.scale_factor(),
+            is_primary: monitor.is_primary(),
+        }
+    }
+}
+
+/// A captured image together with where it sits on the desktop.
+pub struct CapturedFrame {
+    pub image: RgbaImage,
+    // Image pixels per desktop unit
+    pub scale: f64,
+    // Desktop position of the image's top-left corner
+    pub x: i32,
+    pub y: i32,
+    // Source monitor, None for stitched monitors and window captures
+    pub monitor: Option<MonitorInfo>,
+}
+
+impl CapturedFrame {
+    /// Size of the captured area in desktop units.
+    pub fn desktop_size(&self) -> (f64, f64) {
+        (
+            self.image.width() as f64 / self.scale,
+            self.image.height() as f64 / self.scale,
+        )
+    }
+}
+
+// Which monitor(s) a capture should read from. Defaults to the primary monitor.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum MonitorTarget {
+    #[default]
+    Primary,
+    Id { id: u32 },
+    UnderCursor,
+    All,
+}
+
+pub fn all_monitors() -> Result<Vec<Monitor>, String> {
+    Monitor::all().map_err(|e| format!("Failed to get monitors: {}", e))
+}
+
+/// Resolves a single-monitor target. `MonitorTarget::All` is rejected here,
+/// callers that support stitching should check for it first.
+pub fn resolve_monitor(app: &AppHandle, target: &MonitorTarget) -> Result<Monitor, String> {
+    match target {
+        MonitorTarget::Primary => all_monitors()?
+            .into_iter()
+            .find(|m| m.is_primary())
+            .ok_or("No primary monitor found".to_string()),
+        MonitorTarget::Id { id } => all_monitors()?
+            .into_iter()
+            .find(|m| m.id() == *id)
+            .ok_or(format!("Monitor {} not found", id)),
+        MonitorTarget::UnderCursor => {
+            let position = app
+                .cursor_position()
+                .map_err(|e| format!("Failed to get cursor position: {}", e))?;
+            Monitor::from_point(position.x as i32, position.y as i32)
+                .map_err(|e| format!("Failed to find monitor under cursor: {}", e))
+        }
+        MonitorTarget::All => Err("Expected a single monitor target".to_string()),
+    }
+}
+
+pub fn capture_monitor(monitor: &Monitor) -> Result<RgbaImage, String> {
+    monitor
+        .capture_image()
+        .map_err(|e| format!("Failed to capture image: {}", e))
+}
+
+/// Captures every monitor and lays them out on one canvas according to
+/// their desktop positions. Monitors with a lower pixel density are upscaled
+/// to the densest one so the layout stays proportional.
+pub fn capture_all_stitched() -> Result<CapturedFrame, String> {
+    let monitors = all_monitors()?;
+    if monitors.is_empty() {
+        return Err("No monitors found".to_string());
+    }
+
+    let mut captures = Vec::with_capacity(monitors.len());
+    for monitor in &monitors {
+        captures.push((MonitorInfo::from(monitor), capture_monitor(monitor)?));
+    }
+
+    // Pixels per desktop unit, taken from the densest monitor
+    let scale = captures
+        .iter()
+        .map(|(info, image)| image.width() as f64 / info.width.max(1) as f64)
+        .fold(1.0_f64, f64::max);
+
+    let min_x = captures.iter().map(|(info, _)| info.x).min().unwrap_or(0);
+    let min_y = captures.iter().map(|(info, _)| info.y).min().unwrap_or(0);
+    let max_x = captures
+        .iter()
+        .map(|(info, _)| info.x + info.width as i32)
+        .max()
+        .unwrap_or(0);
+    let max_y = captures
+        .iter()
+        .map(|(info, _)| info.y + info.height as i32)
+        .max()
+        .unwrap_or(0);
+
+    let canvas_width = ((max_x - min_x) as f64 * scale).round() as u32;
+    let canvas_height = ((max_y - min_y) as f64 * scale).round() as u32;
+    let mut canvas = RgbaImage::new(canvas_width, canvas_height);
+
+    for (info, image) in captures {
+        let target_width = (info.width as f64 * scale).round() as u32;
+        let target_height = (info.height as f64 * scale).round() as u32;
+        let image = if image.width() != target_width || image.height() != target_height {
+            imageops::resize(&image, target_width, target_height, imageops::FilterType::Triangle)
+        } else {
+            image
+        };
+
+        let offset_x = ((info.x - min_x) as f64 * scale).round() as i64;
+        let offset_y = ((info.y - min_y) as f64 * scale).round() as i64;
+        imageops::overlay(&mut canvas, &image, offset_x, offset_y);
+    }
+
+    Ok(CapturedFrame {
+        image: canvas,
+        scale,
+        x: min_x,
+        y: min_y,
+        monitor: None,
+    })
+}
+
+/// Captures the requested target, stitching all monitors when asked to. Also
+/// reports the desktop origin and how many image pixels make up one desktop
+/// unit (the scale factor on HiDPI displays).
+pub fn capture_target_frame(app: &AppHandle, target: &MonitorTarget) -> Result<CapturedFrame, String> {
+    match target {
+        MonitorTarget::All => capture_all_stitched(),
+        _ => {
+            let monitor = resolve_monitor(app, target)?;
+            let image = capture_monitor(&monitor)?;
+            let scale = image.width() as f64 / monitor.width().max(1) as f64;
+            Ok(CapturedFrame {
+                image,
+                scale,
+                x: monitor.x(),
+                y: monitor.y(),
+                monitor: Some(MonitorInfo::from(&monitor)),
+            })
+        }
+    }
+}
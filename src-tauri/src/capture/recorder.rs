@@ -0,0 +1,327 @@
+// Rolling screen recording: keeps the last few seconds of frames in memory
+// and exports them as an animated GIF or a sequence of still images.
+//
+// Animated WebP isn't offered because the image crate only encodes still WebP.
+
+use base64::Engine;
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+use super::encode::{self, EncodeOptions, EncodedImage};
+use super::monitor::MonitorTarget;
+
+const MAX_FPS: f64 = 10.0;
+// NeuQuant speed for GIF palettes, 1 is best quality and very slow
+const GIF_SPEED: i32 = 10;
+const MAX_SHRINK_ATTEMPTS: u32 = 12;
+// Smaller frames aren't worth recording or exporting
+const MIN_LONG_EDGE: u32 = 16;
+
+fn check_long_edge(max_long_edge: u32) -> Result<(), String> {
+    if max_long_edge < MIN_LONG_EDGE {
+        return Err(format!("max_long_edge must be at least {} pixels", MIN_LONG_EDGE));
+    }
+    Ok(())
+}
+
+pub struct RecordedFrame {
+    image: RgbaImage,
+    captured_at: Instant,
+}
+
+pub type FrameBuffer = Arc<Mutex<VecDeque<RecordedFrame>>>;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct RecordingOptions {
+    pub fps: f64,
+    // How much history to keep in memory
+    pub max_seconds: u64,
+    // Frames are downscaled as they're buffered to bound memory use
+    pub max_long_edge: u32,
+    pub target: MonitorTarget,
+}
+
+impl Default for RecordingOptions {
+    fn default() -> Self {
+        RecordingOptions {
+            fps: 2.0,
+            max_seconds: 30,
+            max_long_edge: 960,
+            target: MonitorTarget::default(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordingFormat {
+    #[default]
+    Gif,
+    Frames,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ExportOptions {
+    pub format: RecordingFormat,
+    // Budget for the whole export, split across frames for `Frames`
+    pub max_bytes: usize,
+    pub max_long_edge: u32,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        ExportOptions {
+            format: RecordingFormat::Gif,
+            max_bytes: 5_000_000,
+            max_long_edge: 640,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecordingExport {
+    pub format: RecordingFormat,
+    // Set for GIF exports
+    pub gif_base64: Option<String>,
+    pub mime_type: Option<String>,
+    // Set for frame sequence exports
+    pub frames: Vec<EncodedImage>,
+    pub frame_count: usize,
+    pub duration_ms: u64,
+    pub width: u32,
+    pub height: u32,
+    pub byte_size: usize,
+}
+
+/// Per-frame display time, taken from the gap to the following frame.
+fn frame_delays(frames: &[(RgbaImage, Instant)], nominal: Duration) -> Vec<Duration> {
+    (0..frames.len())
+        .map(|i| match frames.get(i + 1) {
+            Some((_, next)) => next.duration_since(frames[i].1),
+            None => nominal,
+        })
+        .collect()
+}
+
+fn encode_gif(frames: &[RgbaImage], delays: &[Duration]) -> Result<Vec<u8>, String> {
+    let mut buffer = Vec::new();
+    {
+        let mut encoder = GifEncoder::new_with_speed(&mut buffer, GIF_SPEED);
+        encoder
+            .set_repeat(Repeat::Infinite)
+            .map_err(|e| format!("Failed to configure GIF: {}", e))?;
+
+        let gif_frames = frames.iter().zip(delays).map(|(image, delay)| {
+            Frame::from_parts(image.clone(), 0, 0, Delay::from_saturating_duration(*delay))
+        });
+        encoder
+            .encode_frames(gif_frames)
+            .map_err(|e| format!("Failed to encode GIF: {}", e))?;
+    }
+    Ok(buffer)
+}
+
+/// Encodes a GIF under the byte budget by dropping every other frame and
+/// then shrinking the frames until it fits.
+fn export_gif(mut frames: Vec<(RgbaImage, Instant)>, options: &ExportOptions, nominal: Duration) -> Result<(Vec<u8>, usize, u32, u32), String> {
+    let mut long_edge = options.max_long_edge;
+
+    for _ in 0..MAX_SHRINK_ATTEMPTS {
+        let scaled: Vec<RgbaImage> = frames.iter().map(|(image, _)| encode::thumbnail(image, long_edge)).collect();
+        let gif = encode_gif(&scaled, &frame_delays(&frames, nominal))?;
+
+        if gif.len() <= options.max_bytes {
+            let (width, height) = scaled.first().map_or((0, 0), |f| (f.width(), f.height()));
+            return Ok((gif, scaled.len(), width, height));
+        }
+
+        if frames.len() > 4 {
+            frames = frames.into_iter().step_by(2).collect();
+        } else if long_edge > MIN_LONG_EDGE {
+            long_edge = ((long_edge as f64 * 0.8) as u32).max(MIN_LONG_EDGE);
+        } else {
+            break;
+        }
+    }
+
+    Err("Failed to fit recording within the byte budget".to_string())
+}
+
+#[tauri::command]
+pub async fn start_screen_recording(app: AppHandle, options: Option<RecordingOptions>) -> Result<(), String> {
+    let state = app.state::<crate::ScreenRecorderState>();
+    let mut guard = state.record_task.lock().unwrap();
+
+    if guard.is_some() {
+        return Err("Screen recording already running".to_string());
+    }
+
+    let options = options.unwrap_or_default();
+    check_long_edge(options.max_long_edge)?;
+    let fps = options.fps.clamp(0.1, MAX_FPS);
+    let max_frames = ((options.max_seconds as f64 * fps).ceil() as usize).max(1);
+    let frames = state.frames.clone();
+    frames.lock().unwrap().clear();
+
+    let app_clone = app.clone();
+    let task = tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs_f64(1.0 / fps));
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        loop {
+            ticker.tick().await;
+
+            let capture_app = app_clone.clone();
+            let target = options.target.clone();
+            let max_long_edge = options.max_long_edge;
+            let captured = tokio::task::spawn_blocking(move || {
+                super::capture_image(&capture_app, &target).map(|image| encode::thumbnail(&image, max_long_edge))
+            })
+            .await;
+
+            match captured {
+                Ok(Ok(image)) => {
+                    let mut buffer = frames.lock().unwrap();
+                    buffer.push_back(RecordedFrame {
+                        image,
+                        captured_at: Instant::now(),
+                    });
+                    while buffer.len() > max_frames {
+                        buffer.pop_front();
+                    }
+                }
+                Ok(Err(e)) => eprintln!("Screen recording capture failed: {}", e),
+                Err(e) => eprintln!("Screen recording task failed: {}", e),
+            }
+        }
+    });
+
+    *guard = Some(task);
+    Ok(())
+}
+
+// Stops sampling but keeps the buffered frames so they can still be exported
+#[tauri::command]
+pub async fn stop_screen_recording(app: AppHandle) -> Result<(), String> {
+    let state = app.state::<crate::ScreenRecorderState>();
+    let mut guard = state.record_task.lock().unwrap();
+
+    if let Some(task) = guard.take() {
+        task.abort();
+    }
+    Ok(())
+}
+
+/// Exports the last `seconds` of buffered frames.
+#[tauri::command]
+pub async fn export_screen_recording(
+    app: AppHandle,
+    seconds: f64,
+    options: Option<ExportOptions>,
+) -> Result<RecordingExport, String> {
+    let options = options.unwrap_or_default();
+    check_long_edge(options.max_long_edge)?;
+    let window = Duration::try_from_secs_f64(seconds)
+        .map_err(|e| format!("Invalid recording window of {} seconds: {}", seconds, e))?;
+    let state = app.state::<crate::ScreenRecorderState>();
+
+    let now = Instant::now();
+    let frames: Vec<(RgbaImage, Instant)> = state
+        .frames
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|f| now.duration_since(f.captured_at) <= window)
+        .map(|f| (f.image.clone(), f.captured_at))
+        .collect();
+
+    if frames.is_empty() {
+        return Err("No recorded frames in the requested window".to_string());
+    }
+
+    // Nominal frame time, used for the last frame's delay
+    let nominal = frame_delays(&frames, Duration::from_millis(500))
+        .first()
+        .copied()
+        .unwrap_or(Duration::from_millis(500));
+    let duration_ms = frames
+        .last()
+        .zip(frames.first())
+        .map_or(0, |(last, first)| last.1.duration_since(first.1).as_millis() as u64)
+        + nominal.as_millis() as u64;
+
+    tokio::task::spawn_blocking(move || match options.format {
+        RecordingFormat::Gif => {
+            let (gif, frame_count, width, height) = export_gif(frames, &options, nominal)?;
+            Ok(RecordingExport {
+                format: RecordingFormat::Gif,
+                byte_size: gif.len(),
+                gif_base64: Some(base64::engine::general_purpose::STANDARD.encode(gif)),
+                mime_type: Some("image/gif".to_string()),
+                frames: Vec::new(),
+                frame_count,
+                duration_ms,
+                width,
+                height,
+            })
+        }
+        RecordingFormat::Frames => {
+            let encode_options = EncodeOptions {
+                max_bytes: Some(options.max_bytes / frames.len()),
+                max_long_edge: Some(options.max_long_edge),
+                ..EncodeOptions::default()
+            };
+            let encoded = frames
+                .iter()
+                .map(|(image, _)| encode::encode_image(image, &encode_options))
+                .collect::<Result<Vec<_>, String>>()?;
+
+            let (width, height) = encoded.first().map_or((0, 0), |f| (f.width, f.height));
+            Ok(RecordingExport {
+                format: RecordingFormat::Frames,
+                gif_base64: None,
+                mime_type: None,
+                byte_size: encoded.iter().map(|f| f.byte_size).sum(),
+                frame_count: encoded.len(),
+                frames: encoded,
+                duration_ms,
+                width,
+                height,
+            })
+        }
+    })
+    .await
+    .map_err(|e| format!("Recording export task failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frames(count: usize, width: u32, height: u32) -> Vec<(RgbaImage, Instant)> {
+        let start = Instant::now();
+        (0..count)
+            .map(|i| (RgbaImage::new(width, height), start + Duration::from_millis(500 * i as u64)))
+            .collect()
+    }
+
+    #[test]
+    fn gif_export_keeps_aspect_ratio() {
+        let options = ExportOptions { max_long_edge: 160, ..ExportOptions::default() };
+        let (_, frame_count, width, height) = export_gif(frames(3, 1920, 1080), &options, Duration::from_millis(500)).unwrap();
+        assert_eq!((frame_count, width, height), (3, 160, 90));
+    }
+
+    #[test]
+    fn gif_export_gives_up_at_the_smallest_size() {
+        let options = ExportOptions { max_bytes: 1, max_long_edge: 64, ..ExportOptions::default() };
+        assert!(export_gif(frames(2, 640, 480), &options, Duration::from_millis(500)).is_err());
+    }
+}
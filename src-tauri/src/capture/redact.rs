@@ -0,0 +1,449 @@
+// Privacy redaction of captures before they are encoded
+//
+// Regions come from three places: rectangles the user marked, windows whose
+// title or app name is blocked, and OCR text matching sensitive patterns.
+// If OCR is requested and fails we return an error rather than unredacted pixels.
+//
+// The saved settings apply to every capture, the history and the recorder
+// included. With the default settings nothing is redacted.
+
+use image::{imageops, Rgba, RgbaImage};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use tauri::{AppHandle, Manager};
+
+use super::monitor::CapturedFrame;
+use super::window;
+use crate::ocr::{self, OcrOptions, OcrResult};
+
+const BLUR_SIGMA: f32 = 12.0;
+// Padding around OCR boxes so glyph edges don't survive the redaction
+const PATTERN_PADDING: u32 = 2;
+
+const EMAIL_PATTERN: &str = r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}";
+const API_KEY_PATTERN: &str = r"\b(?:sk|pk|rk)-[A-Za-z0-9_-]{16,}|\bAKIA[0-9A-Z]{16}\b|\bgh[pousr]_[A-Za-z0-9]{30,}|\bAIza[0-9A-Za-z_-]{35}|\bxox[baprs]-[A-Za-z0-9-]{10,}";
+const CARD_NUMBER_PATTERN: &str = r"\b(?:\d[ -]?){12,18}\d\b";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RedactionStyle {
+    #[default]
+    Blur,
+    Blackout,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SensitivePattern {
+    Email,
+    ApiKey,
+    CardNumber,
+}
+
+/// Rectangle in desktop units, relative to the capture target's top-left corner.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RedactionRect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct RedactionOptions {
+    pub style: RedactionStyle,
+    pub rectangles: Vec<RedactionRect>,
+    // Case-insensitive substrings matched against window titles and app names
+    pub blocked_windows: Vec<String>,
+    pub patterns: Vec<SensitivePattern>,
+    // Extra regular expressions matched against OCR text
+    pub custom_patterns: Vec<String>,
+    pub ocr: OcrOptions,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RedactionReason {
+    Rectangle,
+    Window { title: String, app_name: String },
+    // The matched text itself is never reported
+    Pattern { pattern: String },
+}
+
+/// A redacted area in pixels of the captured image.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RedactedRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub reason: RedactionReason,
+}
+
+pub struct RedactionState {
+    settings: RwLock<RedactionOptions>,
+}
+
+impl RedactionState {
+    pub fn new(settings: RedactionOptions) -> Self {
+        RedactionState {
+            settings: RwLock::new(settings),
+        }
+    }
+
+    pub fn settings(&self) -> RedactionOptions {
+        self.settings.read().unwrap().clone()
+    }
+}
+
+impl RedactionOptions {
+    /// Adds `extra` to these options, `extra` choosing the style and OCR language.
+    pub fn merged(mut self, extra: RedactionOptions) -> RedactionOptions {
+        self.style = extra.style;
+        self.rectangles.extend(extra.rectangles);
+        self.blocked_windows.extend(extra.blocked_windows);
+        for pattern in extra.patterns {
+            if !self.patterns.contains(&pattern) {
+                self.patterns.push(pattern);
+            }
+        }
+        self.custom_patterns.extend(extra.custom_patterns);
+        self.ocr.language = extra.ocr.language.or(self.ocr.language);
+        self
+    }
+
+    fn needs_ocr(&self) -> bool {
+        !self.patterns.is_empty() || !self.custom_patterns.is_empty()
+    }
+
+    fn compiled_patterns(&self) -> Result<Vec<(String, Regex)>, String> {
+        let mut compiled = Vec::new();
+        for pattern in &self.patterns {
+            let (name, source) = match pattern {
+                SensitivePattern::Email => ("email", EMAIL_PATTERN),
+                SensitivePattern::ApiKey => ("api_key", API_KEY_PATTERN),
+                SensitivePattern::CardNumber => ("card_number", CARD_NUMBER_PATTERN),
+            };
+            let regex = Regex::new(source).map_err(|e| format!("Invalid {} pattern: {}", name, e))?;
+            compiled.push((name.to_string(), regex));
+        }
+        for source in &self.custom_patterns {
+            let regex = Regex::new(source).map_err(|e| format!("Invalid custom pattern '{}': {}", source, e))?;
+            compiled.push(("custom".to_string(), regex));
+        }
+        Ok(compiled)
+    }
+}
+
+// Luhn checksum, filters out most digit runs that aren't card numbers
+fn passes_luhn(candidate: &str) -> bool {
+    let digits: Vec<u32> = candidate.chars().filter_map(|c| c.to_digit(10)).collect();
+    if digits.len() < 13 {
+        return false;
+    }
+
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, d)| {
+            if i % 2 == 1 {
+                let doubled = d * 2;
+                if doubled > 9 { doubled - 9 } else { doubled }
+            } else {
+                *d
+            }
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+/// Clamps a desktop-unit rectangle to the frame and converts it to image pixels.
+fn to_image_rect(frame: &CapturedFrame, x: f64, y: f64, width: f64, height: f64) -> Option<(u32, u32, u32, u32)> {
+    let left = (x * frame.scale).floor().max(0.0) as u32;
+    let top = (y * frame.scale).floor().max(0.0) as u32;
+    let right = (((x + width) * frame.scale).ceil().max(0.0) as u32).min(frame.image.width());
+    let bottom = (((y + height) * frame.scale).ceil().max(0.0) as u32).min(frame.image.height());
+
+    if left >= right || top >= bottom {
+        return None;
+    }
+    Some((left, top, right - left, bottom - top))
+}
+
+fn window_regions(app: &AppHandle, frame: &CapturedFrame, blocked: &[String]) -> Result<Vec<RedactedRegion>, String> {
+    let blocked: Vec<String> = blocked.iter().map(|b| b.to_lowercase()).filter(|b| !b.is_empty()).collect();
+    if blocked.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut regions = Vec::new();
+    for window in window::other_windows(app)? {
+        if window.is_minimized() {
+            continue;
+        }
+
+        let title = window.title().to_lowercase();
+        let app_name = window.app_name().to_lowercase();
+        if !blocked.iter().any(|b| title.contains(b) || app_name.contains(b)) {
+            continue;
+        }
+
+        let rect = to_image_rect(
+            frame,
+            (window.x() - frame.x) as f64,
+            (window.y() - frame.y) as f64,
+            window.width() as f64,
+            window.height() as f64,
+        );
+        if let Some((x, y, width, height)) = rect {
+            regions.push(RedactedRegion {
+                x,
+                y,
+                width,
+                height,
+                reason: RedactionReason::Window {
+                    title: window.title().to_string(),
+                    app_name: window.app_name().to_string(),
+                },
+            });
+        }
+    }
+    Ok(regions)
+}
+
+/// Matches patterns against each OCR line and redacts the words a match touches,
+/// so values split across words (like spaced card numbers) are still covered.
+fn pattern_regions(ocr_result: &OcrResult, patterns: &[(String, Regex)], image: &RgbaImage) -> Vec<RedactedRegion> {
+    let mut regions = Vec::new();
+
+    for line in &ocr_result.lines {
+        // Character span of each word inside the joined line text
+        let mut spans = Vec::with_capacity(line.words.len());
+        let mut offset = 0;
+        for index in &line.words {
+            let length = ocr_result.words[*index].text.len();
+            spans.push((*index, offset, offset + length));
+            offset += length + 1;
+        }
+
+        for (name, regex) in patterns {
+            for found in regex.find_iter(&line.text) {
+                if name == "card_number" && !passes_luhn(found.as_str()) {
+                    continue;
+                }
+
+                let touched: Vec<_> = spans
+                    .iter()
+                    .filter(|(_, start, end)| *start < found.end() && found.start() < *end)
+                    .map(|(index, _, _)| &ocr_result.words[*index])
+                    .collect();
+                if touched.is_empty() {
+                    continue;
+                }
+
+                let left = touched.iter().map(|w| w.x).min().unwrap_or(0).saturating_sub(PATTERN_PADDING);
+                let top = touched.iter().map(|w| w.y).min().unwrap_or(0).saturating_sub(PATTERN_PADDING);
+                let right = (touched.iter().map(|w| w.x + w.width).max().unwrap_or(0) + PATTERN_PADDING).min(image.width());
+                let bottom = (touched.iter().map(|w| w.y + w.height).max().unwrap_or(0) + PATTERN_PADDING).min(image.height());
+                if left >= right || top >= bottom {
+                    continue;
+                }
+
+                regions.push(RedactedRegion {
+                    x: left,
+                    y: top,
+                    width: right - left,
+                    height: bottom - top,
+                    reason: RedactionReason::Pattern { pattern: name.clone() },
+                });
+            }
+        }
+    }
+
+    regions
+}
+
+fn apply_region(image: &mut RgbaImage, region: &RedactedRegion, style: RedactionStyle) {
+    match style {
+        RedactionStyle::Blackout => {
+            for y in region.y..region.y + region.height {
+                for x in region.x..region.x + region.width {
+                    image.put_pixel(x, y, Rgba([0, 0, 0, 255]));
+                }
+            }
+        }
+        RedactionStyle::Blur => {
+            let area = imageops::crop_imm(image, region.x, region.y, region.width, region.height).to_image();
+            let blurred = imageops::blur(&area, BLUR_SIGMA);
+            imageops::replace(image, &blurred, region.x as i64, region.y as i64);
+        }
+    }
+}
+
+/// Finds every region to redact in the frame and blurs or blacks them out in place.
+pub fn redact_frame(app: &AppHandle, frame: &mut CapturedFrame, options: &RedactionOptions) -> Result<Vec<RedactedRegion>, String> {
+    let mut regions: Vec<RedactedRegion> = options
+        .rectangles
+        .iter()
+        .filter_map(|rect| to_image_rect(frame, rect.x, rect.y, rect.width, rect.height))
+        .map(|(x, y, width, height)| RedactedRegion {
+            x,
+            y,
+            width,
+            height,
+            reason: RedactionReason::Rectangle,
+        })
+        .collect();
+
+    regions.extend(window_regions(app, frame, &options.blocked_windows)?);
+
+    if options.needs_ocr() {
+        let patterns = options.compiled_patterns()?;
+        let ocr_result = ocr::get_text_recognizer(&options.ocr)
+            .recognize(&frame.image)
+            .map_err(|e| format!("Redaction OCR failed, capture withheld: {}", e))?;
+        regions.extend(pattern_regions(&ocr_result, &patterns, &frame.image));
+    }
+
+    for region in &regions {
+        apply_region(&mut frame.image, region, options.style);
+    }
+
+    Ok(regions)
+}
+
+/// Applies the saved redaction settings to a freshly captured frame.
+pub fn redact_configured(app: &AppHandle, frame: &mut CapturedFrame) -> Result<Vec<RedactedRegion>, String> {
+    let options = app.state::<RedactionState>().settings();
+    redact_frame(app, frame, &options)
+}
+
+fn get_settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let app_data_dir = app.path().app_data_dir()
+        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+
+    fs::create_dir_all(&app_data_dir)
+        .map_err(|e| format!("Failed to create app data directory: {}", e))?;
+
+    Ok(app_data_dir.join("redaction_settings.json"))
+}
+
+/// Reads the saved settings, defaults when none are saved or they can't be parsed.
+pub fn load_settings(app: &AppHandle) -> RedactionOptions {
+    get_settings_path(app)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_settings(app: &AppHandle, settings: RedactionOptions) -> Result<(), String> {
+    // Bad patterns would otherwise fail every capture
+    settings.compiled_patterns()?;
+
+    let content = serde_json::to_string(&settings)
+        .map_err(|e| format!("Failed to serialize redaction settings: {}", e))?;
+    fs::write(get_settings_path(app)?, content)
+        .map_err(|e| format!("Failed to write redaction settings: {}", e))?;
+
+    *app.state::<RedactionState>().settings.write().unwrap() = settings;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ocr::{OcrLine, OcrWord};
+
+    fn frame(width: u32, height: u32, scale: f64) -> CapturedFrame {
+        CapturedFrame { image: RgbaImage::new(width, height), scale, x: 0, y: 0, monitor: None }
+    }
+
+    fn word(text: &str, x: u32) -> OcrWord {
+        OcrWord { text: text.to_string(), x, y: 10, width: 40, height: 20, confidence: 90.0 }
+    }
+
+    fn ocr_result(lines: Vec<Vec<OcrWord>>) -> OcrResult {
+        let mut words = Vec::new();
+        let mut ocr_lines = Vec::new();
+        for line in lines {
+            let indices = (words.len()..words.len() + line.len()).collect();
+            words.extend(line);
+            ocr_lines.push(OcrLine::from_words(&words, indices));
+        }
+        OcrResult { text: String::new(), lines: ocr_lines, words, width: 400, height: 100 }
+    }
+
+    #[test]
+    fn luhn_accepts_card_numbers_only() {
+        assert!(passes_luhn("4111 1111 1111 1111"));
+        assert!(passes_luhn("5500-0000-0000-0004"));
+        assert!(!passes_luhn("4111 1111 1111 1112"));
+        // Too short to be a card even with a valid checksum
+        assert!(!passes_luhn("4242"));
+    }
+
+    #[test]
+    fn image_rect_scales_and_clamps() {
+        let frame = frame(200, 100, 2.0);
+        assert_eq!(to_image_rect(&frame, 10.0, 10.0, 20.0, 5.0), Some((20, 20, 40, 10)));
+        // Partly off the top-left and bottom-right edges
+        assert_eq!(to_image_rect(&frame, -5.0, -5.0, 10.0, 10.0), Some((0, 0, 10, 10)));
+        assert_eq!(to_image_rect(&frame, 90.0, 40.0, 50.0, 50.0), Some((180, 80, 20, 20)));
+        // Fractional edges round outwards
+        assert_eq!(to_image_rect(&frame, 0.3, 0.3, 1.0, 1.0), Some((0, 0, 3, 3)));
+        assert_eq!(to_image_rect(&frame, 150.0, 0.0, 10.0, 10.0), None);
+        assert_eq!(to_image_rect(&frame, 10.0, 10.0, 0.0, 10.0), None);
+    }
+
+    #[test]
+    fn pattern_regions_cover_the_words_a_match_touches() {
+        let options = RedactionOptions {
+            patterns: vec![SensitivePattern::CardNumber, SensitivePattern::Email],
+            ..RedactionOptions::default()
+        };
+        let patterns = options.compiled_patterns().unwrap();
+        let result = ocr_result(vec![
+            vec![word("Card", 0), word("4111", 50), word("1111", 100), word("1111", 150), word("1111", 200)],
+            vec![word("1234", 0), word("5678", 50), word("9012", 100), word("3456", 150)],
+            vec![word("mail", 0), word("me@example.com", 380)],
+        ]);
+        let image = RgbaImage::new(400, 100);
+
+        let regions = pattern_regions(&result, &patterns, &image);
+        let boxes: Vec<_> = regions.iter().map(|r| (r.x, r.y, r.width, r.height)).collect();
+        // The failed Luhn check on the second line is skipped, the email box
+        // is clamped to the image width
+        assert_eq!(boxes, vec![(48, 8, 194, 24), (378, 8, 22, 24)]);
+        assert!(matches!(&regions[0].reason, RedactionReason::Pattern { pattern } if pattern == "card_number"));
+        assert!(matches!(&regions[1].reason, RedactionReason::Pattern { pattern } if pattern == "email"));
+    }
+
+    #[test]
+    fn merged_options_run_one_pass() {
+        let saved = RedactionOptions {
+            patterns: vec![SensitivePattern::Email],
+            blocked_windows: vec!["Bank".to_string()],
+            ocr: OcrOptions { language: Some("deu".to_string()) },
+            ..RedactionOptions::default()
+        };
+        let extra = RedactionOptions {
+            style: RedactionStyle::Blackout,
+            patterns: vec![SensitivePattern::Email, SensitivePattern::ApiKey],
+            custom_patterns: vec!["secret-\\d+".to_string()],
+            ..RedactionOptions::default()
+        };
+
+        let merged = saved.merged(extra);
+        assert_eq!(merged.style, RedactionStyle::Blackout);
+        assert_eq!(merged.patterns, vec![SensitivePattern::Email, SensitivePattern::ApiKey]);
+        assert_eq!(merged.blocked_windows, vec!["Bank"]);
+        assert_eq!(merged.custom_patterns, vec!["secret-\\d+"]);
+        assert_eq!(merged.ocr.language.as_deref(), Some("deu"));
+    }
+}
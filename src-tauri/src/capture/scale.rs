@@ -0,0 +1,83 @@
+// Model-sized captures for computer use
+//
+// Vision models work best (and click most accurately) on screenshots around
+// XGA/WXGA size. We downscale to one of those targets and report the factors
+// needed to turn the model's coordinates back into screen coordinates.
+
+use image::{imageops, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::computer_use::CoordinateScale;
+
+// Candidate targets for `ModelResolution::Auto`
+const XGA: (u32, u32) = (1024, 768);
+const WXGA: (u32, u32) = (1280, 800);
+const FWXGA: (u32, u32) = (1366, 768);
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(tag = "preset", rename_all = "lowercase")]
+pub enum ModelResolution {
+    // Whichever preset is closest to the screen's aspect ratio
+    #[default]
+    Auto,
+    Xga,
+    Wxga,
+    Fwxga,
+    Custom { width: u32, height: u32 },
+}
+
+impl ModelResolution {
+    fn bounds(&self, width: u32, height: u32) -> (u32, u32) {
+        match self {
+            ModelResolution::Auto => {
+                let aspect = width as f64 / height.max(1) as f64;
+                [XGA, WXGA, FWXGA]
+                    .into_iter()
+                    .min_by(|a, b| {
+                        let da = (a.0 as f64 / a.1 as f64 - aspect).abs();
+                        let db = (b.0 as f64 / b.1 as f64 - aspect).abs();
+                        da.total_cmp(&db)
+                    })
+                    .unwrap_or(XGA)
+            }
+            ModelResolution::Xga => XGA,
+            ModelResolution::Wxga => WXGA,
+            ModelResolution::Fwxga => FWXGA,
+            ModelResolution::Custom { width, height } => ((*width).max(1), (*height).max(1)),
+        }
+    }
+}
+
+/// Fits the image inside the resolution's bounds, keeping its aspect ratio.
+/// Images that already fit are returned unchanged; we never upscale.
+pub fn downscale_for_model(image: &RgbaImage, resolution: &ModelResolution) -> RgbaImage {
+    let (max_width, max_height) = resolution.bounds(image.width(), image.height());
+    let scale = (max_width as f64 / image.width().max(1) as f64)
+        .min(max_height as f64 / image.height().max(1) as f64);
+
+    if scale >= 1.0 {
+        return image.clone();
+    }
+
+    let width = ((image.width() as f64 * scale).round() as u32).max(1);
+    let height = ((image.height() as f64 * scale).round() as u32).max(1);
+    imageops::resize(image, width, height, imageops::FilterType::Lanczos3)
+}
+
+/// Scale from an image of `image_width` x `image_height` pixels back to the
+/// desktop area it was captured from.
+pub fn coordinate_scale(
+    image_width: u32,
+    image_height: u32,
+    desktop_width: f64,
+    desktop_height: f64,
+    origin_x: i32,
+    origin_y: i32,
+) -> CoordinateScale {
+    CoordinateScale {
+        scale_x: desktop_width / image_width.max(1) as f64,
+        scale_y: desktop_height / image_height.max(1) as f64,
+        offset_x: origin_x,
+        offset_y: origin_y,
+    }
+}
@@ -0,0 +1,359 @@
+// On-disk capture history under the app data directory
+//
+// Layout: captures/index.json holds the records, each capture is stored as
+// <id>.<ext> next to a small <id>_thumb.jpg for list views.
+
+use base64::Engine;
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, ImageEncoder, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+use uuid::Uuid;
+
+use super::encode::{self, EncodedImage, ImageFormat};
+use super::monitor::MonitorInfo;
+
+const THUMBNAIL_LONG_EDGE: u32 = 320;
+const THUMBNAIL_QUALITY: u8 = 70;
+// Older captures are evicted once the store grows past this
+pub const DEFAULT_MAX_STORE_BYTES: u64 = 500 * 1024 * 1024;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CaptureSource {
+    Manual,
+    Shortcut,
+    Agent,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CaptureRecord {
+    pub id: String,
+    pub timestamp_ms: u64,
+    pub source: CaptureSource,
+    // None for stitched multi-monitor captures
+    pub monitor: Option<MonitorInfo>,
+    pub format: ImageFormat,
+    pub mime_type: String,
+    pub width: u32,
+    pub height: u32,
+    // Image plus thumbnail size on disk
+    pub byte_size: u64,
+    file: String,
+    thumbnail_file: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct CaptureIndex {
+    captures: Vec<CaptureRecord>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CaptureSummary {
+    pub record: CaptureRecord,
+    pub thumbnail_base64: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StoredCapture {
+    pub record: CaptureRecord,
+    pub image_base64: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EvictionResult {
+    pub removed: Vec<String>,
+    pub total_bytes: u64,
+}
+
+fn get_store_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let app_data_dir = app.path().app_data_dir()
+        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+
+    let store_dir = app_data_dir.join("captures");
+    fs::create_dir_all(&store_dir)
+        .map_err(|e| format!("Failed to create capture directory: {}", e))?;
+
+    Ok(store_dir)
+}
+
+fn read_index(store_dir: &Path) -> Result<CaptureIndex, String> {
+    let index_path = store_dir.join("index.json");
+    if !index_path.exists() {
+        return Ok(CaptureIndex::default());
+    }
+
+    let content = fs::read_to_string(&index_path)
+        .map_err(|e| format!("Failed to read capture index: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse capture index: {}", e))
+}
+
+fn write_index(store_dir: &Path, index: &CaptureIndex) -> Result<(), String> {
+    let content = serde_json::to_string(index)
+        .map_err(|e| format!("Failed to serialize capture index: {}", e))?;
+    fs::write(store_dir.join("index.json"), content)
+        .map_err(|e| format!("Failed to write capture index: {}", e))
+}
+
+fn remove_files(store_dir: &Path, record: &CaptureRecord) {
+    for file in [&record.file, &record.thumbnail_file] {
+        if let Err(e) = fs::remove_file(store_dir.join(file)) {
+            eprintln!("Failed to remove capture file {}: {}", file, e);
+        }
+    }
+}
+
+fn encode_thumbnail(image: &RgbaImage) -> Result<Vec<u8>, String> {
+    let thumbnail = encode::thumbnail(image, THUMBNAIL_LONG_EDGE);
+    let rgb_image = DynamicImage::ImageRgba8(thumbnail).to_rgb8();
+
+    let mut buffer = Vec::new();
+    JpegEncoder::new_with_quality(&mut buffer, THUMBNAIL_QUALITY)
+        .write_image(
+            rgb_image.as_raw(),
+            rgb_image.width(),
+            rgb_image.height(),
+            image::ExtendedColorType::Rgb8,
+        )
+        .map_err(|e| format!("Failed to encode thumbnail: {}", e))?;
+    Ok(buffer)
+}
+
+fn extension(format: ImageFormat) -> &'static str {
+    match format {
+        ImageFormat::Jpeg => "jpg",
+        ImageFormat::Png => "png",
+        ImageFormat::Webp => "webp",
+    }
+}
+
+/// Drops the oldest records until the store fits in `max_bytes`. Their files
+/// are left for the caller to remove once the index is written.
+fn evict_records(index: &mut CaptureIndex, max_bytes: u64) -> Vec<CaptureRecord> {
+    index.captures.sort_by_key(|r| r.timestamp_ms);
+
+    let mut total: u64 = index.captures.iter().map(|r| r.byte_size).sum();
+    let mut removed = Vec::new();
+    while total > max_bytes && !index.captures.is_empty() {
+        let record = index.captures.remove(0);
+        total -= record.byte_size;
+        removed.push(record);
+    }
+    removed
+}
+
+/// Writes the capture and thumbnail files, removing any already written on error.
+fn write_files(store_dir: &Path, record: &CaptureRecord, image_bytes: &[u8], thumbnail_bytes: &[u8]) -> Result<(), String> {
+    fs::write(store_dir.join(&record.file), image_bytes)
+        .map_err(|e| format!("Failed to write capture: {}", e))?;
+
+    if let Err(e) = fs::write(store_dir.join(&record.thumbnail_file), thumbnail_bytes) {
+        remove_files(store_dir, record);
+        return Err(format!("Failed to write thumbnail: {}", e));
+    }
+    Ok(())
+}
+
+/// Persists an encoded capture and its thumbnail, then evicts older captures
+/// down to the default limit. A capture larger than the limit is refused.
+pub fn save_capture(
+    app: &AppHandle,
+    encoded: &EncodedImage,
+    source_image: &RgbaImage,
+    source: CaptureSource,
+    monitor: Option<MonitorInfo>,
+) -> Result<CaptureRecord, String> {
+    let image_bytes = base64::engine::general_purpose::STANDARD
+        .decode(&encoded.image_base64)
+        .map_err(|e| format!("Failed to decode capture: {}", e))?;
+    let thumbnail_bytes = encode_thumbnail(source_image)?;
+    let byte_size = (image_bytes.len() + thumbnail_bytes.len()) as u64;
+    // Eviction would otherwise remove it again right away
+    if byte_size > DEFAULT_MAX_STORE_BYTES {
+        return Err(format!("Capture of {} bytes is larger than the history limit", byte_size));
+    }
+
+    let state = app.state::<crate::CaptureStoreState>();
+    let _lock = state.index_lock.lock().unwrap();
+    let store_dir = get_store_dir(app)?;
+    let mut index = read_index(&store_dir)?;
+
+    let id = Uuid::new_v4().to_string();
+    let file = format!("{}.{}", id, extension(encoded.format));
+    let thumbnail_file = format!("{}_thumb.jpg", id);
+
+    let record = CaptureRecord {
+        id,
+        timestamp_ms: super::now_ms(),
+        source,
+        monitor,
+        format: encoded.format,
+        mime_type: encoded.mime_type.clone(),
+        width: encoded.width,
+        height: encoded.height,
+        byte_size,
+        file,
+        thumbnail_file,
+    };
+
+    write_files(&store_dir, &record, &image_bytes, &thumbnail_bytes)?;
+
+    index.captures.push(record.clone());
+    let evicted = evict_records(&mut index, DEFAULT_MAX_STORE_BYTES);
+    if let Err(e) = write_index(&store_dir, &index) {
+        // Without the index entry the files would never be listed or evicted
+        remove_files(&store_dir, &record);
+        return Err(e);
+    }
+    for record in &evicted {
+        remove_files(&store_dir, record);
+    }
+
+    Ok(record)
+}
+
+#[tauri::command]
+pub fn list_captures(app: AppHandle) -> Result<Vec<CaptureSummary>, String> {
+    let state = app.state::<crate::CaptureStoreState>();
+    let _lock = state.index_lock.lock().unwrap();
+    let store_dir = get_store_dir(&app)?;
+    let mut index = read_index(&store_dir)?;
+
+    // Newest first
+    index.captures.sort_by_key(|r| std::cmp::Reverse(r.timestamp_ms));
+
+    Ok(index
+        .captures
+        .into_iter()
+        .map(|record| {
+            let thumbnail_base64 = fs::read(store_dir.join(&record.thumbnail_file))
+                .ok()
+                .map(|bytes| base64::engine::general_purpose::STANDARD.encode(bytes));
+            CaptureSummary {
+                record,
+                thumbnail_base64,
+            }
+        })
+        .collect())
+}
+
+#[tauri::command]
+pub fn get_capture(app: AppHandle, id: String) -> Result<StoredCapture, String> {
+    let state = app.state::<crate::CaptureStoreState>();
+    let _lock = state.index_lock.lock().unwrap();
+    let store_dir = get_store_dir(&app)?;
+    let index = read_index(&store_dir)?;
+
+    let record = index
+        .captures
+        .into_iter()
+        .find(|r| r.id == id)
+        .ok_or(format!("Capture {} not found", id))?;
+
+    let bytes = fs::read(store_dir.join(&record.file))
+        .map_err(|e| format!("Failed to read capture: {}", e))?;
+
+    Ok(StoredCapture {
+        record,
+        image_base64: base64::engine::general_purpose::STANDARD.encode(bytes),
+    })
+}
+
+#[tauri::command]
+pub fn delete_capture(app: AppHandle, id: String) -> Result<(), String> {
+    let state = app.state::<crate::CaptureStoreState>();
+    let _lock = state.index_lock.lock().unwrap();
+    let store_dir = get_store_dir(&app)?;
+    let mut index = read_index(&store_dir)?;
+
+    let position = index
+        .captures
+        .iter()
+        .position(|r| r.id == id)
+        .ok_or(format!("Capture {} not found", id))?;
+
+    let record = index.captures.remove(position);
+    write_index(&store_dir, &index)?;
+    remove_files(&store_dir, &record);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn evict_captures(app: AppHandle, max_bytes: Option<u64>) -> Result<EvictionResult, String> {
+    let state = app.state::<crate::CaptureStoreState>();
+    let _lock = state.index_lock.lock().unwrap();
+    let store_dir = get_store_dir(&app)?;
+    let mut index = read_index(&store_dir)?;
+
+    let evicted = evict_records(&mut index, max_bytes.unwrap_or(DEFAULT_MAX_STORE_BYTES));
+    write_index(&store_dir, &index)?;
+    for record in &evicted {
+        remove_files(&store_dir, record);
+    }
+
+    Ok(EvictionResult {
+        removed: evicted.into_iter().map(|r| r.id).collect(),
+        total_bytes: index.captures.iter().map(|r| r.byte_size).sum(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(id: &str, timestamp_ms: u64, byte_size: u64) -> CaptureRecord {
+        CaptureRecord {
+            id: id.to_string(),
+            timestamp_ms,
+            source: CaptureSource::Manual,
+            monitor: None,
+            format: ImageFormat::Jpeg,
+            mime_type: "image/jpeg".to_string(),
+            width: 10,
+            height: 10,
+            byte_size,
+            file: format!("{}.jpg", id),
+            thumbnail_file: format!("{}_thumb.jpg", id),
+        }
+    }
+
+    fn index(records: Vec<CaptureRecord>) -> CaptureIndex {
+        CaptureIndex { captures: records }
+    }
+
+    fn ids(records: &[CaptureRecord]) -> Vec<&str> {
+        records.iter().map(|r| r.id.as_str()).collect()
+    }
+
+    #[test]
+    fn evicts_oldest_first() {
+        let mut index = index(vec![record("new", 300, 40), record("old", 100, 40), record("mid", 200, 40)]);
+        let evicted = evict_records(&mut index, 50);
+
+        assert_eq!(ids(&evicted), vec!["old", "mid"]);
+        assert_eq!(ids(&index.captures), vec!["new"]);
+    }
+
+    #[test]
+    fn keeps_everything_within_the_limit() {
+        let mut index = index(vec![record("a", 100, 30), record("b", 200, 20)]);
+        assert!(evict_records(&mut index, 50).is_empty());
+        assert_eq!(ids(&index.captures), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn zero_limit_empties_the_store() {
+        let mut index = index(vec![record("a", 100, 30), record("b", 200, 20)]);
+        assert_eq!(evict_records(&mut index, 0).len(), 2);
+        assert!(index.captures.is_empty());
+    }
+
+    #[test]
+    fn thumbnails_keep_aspect_ratio() {
+        let bytes = encode_thumbnail(&RgbaImage::new(1920, 1080)).unwrap();
+        let thumbnail = image::load_from_memory(&bytes).unwrap();
+        assert_eq!((thumbnail.width(), thumbnail.height()), (THUMBNAIL_LONG_EDGE, 180));
+    }
+}
@@ -0,0 +1,170 @@
+// Change-triggered screen capture: samples the screen on an interval and emits
+// `screen-changed` with a freshly encoded frame when enough of it has changed.
+
+use image::{imageops, DynamicImage, GrayImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::encode::{self, EncodeOptions, EncodedImage};
+use super::monitor::MonitorTarget;
+
+const MIN_INTERVAL_MS: u64 = 250;
+// Frames are compared on a TILE_GRID x TILE_GRID grid of averaged tiles
+const TILE_GRID: u32 = 16;
+const TILE_SIZE: u32 = 4;
+// Mean brightness delta (0-255) above which a tile counts as changed
+const TILE_CHANGE_THRESHOLD: u8 = 10;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct WatchOptions {
+    pub interval_ms: u64,
+    // Fraction of tiles (0.0 - 1.0) that must change to emit a frame
+    pub threshold: f64,
+    pub target: MonitorTarget,
+    pub encode: EncodeOptions,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        WatchOptions {
+            interval_ms: 2000,
+            threshold: 0.1,
+            target: MonitorTarget::default(),
+            encode: EncodeOptions::default(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScreenChangedEvent {
+    pub image: EncodedImage,
+    pub change_ratio: f64,
+    pub timestamp_ms: u64,
+}
+
+/// Coarse grayscale fingerprint of a frame, cheap to compare.
+struct FrameSignature(Vec<u8>);
+
+impl FrameSignature {
+    fn from_image(image: &RgbaImage) -> Self {
+        let side = TILE_GRID * TILE_SIZE;
+        let gray: GrayImage = DynamicImage::ImageRgba8(imageops::thumbnail(image, side, side)).to_luma8();
+
+        let mut tiles = Vec::with_capacity((TILE_GRID * TILE_GRID) as usize);
+        for tile_y in 0..TILE_GRID {
+            for tile_x in 0..TILE_GRID {
+                let mut sum = 0u32;
+                for y in 0..TILE_SIZE {
+                    for x in 0..TILE_SIZE {
+                        sum += gray.get_pixel(tile_x * TILE_SIZE + x, tile_y * TILE_SIZE + y)[0] as u32;
+                    }
+                }
+                tiles.push((sum / (TILE_SIZE * TILE_SIZE)) as u8);
+            }
+        }
+
+        FrameSignature(tiles)
+    }
+
+    /// Fraction of tiles whose brightness changed noticeably.
+    fn change_ratio(&self, other: &FrameSignature) -> f64 {
+        let changed = self
+            .0
+            .iter()
+            .zip(other.0.iter())
+            .filter(|(a, b)| a.abs_diff(**b) > TILE_CHANGE_THRESHOLD)
+            .count();
+        changed as f64 / self.0.len().max(1) as f64
+    }
+}
+
+#[tauri::command]
+pub async fn start_screen_watch(app: AppHandle, options: Option<WatchOptions>) -> Result<(), String> {
+    let state = app.state::<crate::ScreenWatchState>();
+    let mut guard = state.watch_task.lock().unwrap();
+
+    if guard.is_some() {
+        return Err("Screen watch already running".to_string());
+    }
+
+    let options = options.unwrap_or_default();
+    let interval = Duration::from_millis(options.interval_ms.max(MIN_INTERVAL_MS));
+
+    let app_clone = app.clone();
+    let task = tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        let mut last_signature: Option<FrameSignature> = None;
+
+        loop {
+            ticker.tick().await;
+
+            let capture_app = app_clone.clone();
+            let target = options.target.clone();
+            let captured = tokio::task::spawn_blocking(move || {
+                super::capture_image(&capture_app, &target).map(|image| {
+                    let signature = FrameSignature::from_image(&image);
+                    (image, signature)
+                })
+            })
+            .await;
+
+            let (image, signature) = match captured {
+                Ok(Ok(captured)) => captured,
+                Ok(Err(e)) => {
+                    eprintln!("Screen watch capture failed: {}", e);
+                    continue;
+                }
+                Err(e) => {
+                    eprintln!("Screen watch task failed: {}", e);
+                    continue;
+                }
+            };
+
+            // The first frame only establishes the baseline
+            let change_ratio = match &last_signature {
+                Some(last) => signature.change_ratio(last),
+                None => {
+                    last_signature = Some(signature);
+                    continue;
+                }
+            };
+
+            if change_ratio < options.threshold {
+                continue;
+            }
+            last_signature = Some(signature);
+
+            let encode_options = options.encode.clone();
+            let encoded = tokio::task::spawn_blocking(move || encode::encode_image(&image, &encode_options)).await;
+            match encoded {
+                Ok(Ok(image)) => {
+                    let event = ScreenChangedEvent {
+                        image,
+                        change_ratio,
+                        timestamp_ms: super::now_ms(),
+                    };
+                    let _ = app_clone.emit("screen-changed", event).map_err(|e| eprintln!("emit screen-changed failed: {}", e));
+                }
+                Ok(Err(e)) => eprintln!("Screen watch encode failed: {}", e),
+                Err(e) => eprintln!("Screen watch task failed: {}", e),
+            }
+        }
+    });
+
+    *guard = Some(task);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn stop_screen_watch(app: AppHandle) -> Result<(), String> {
+    let state = app.state::<crate::ScreenWatchState>();
+    let mut guard = state.watch_task.lock().unwrap();
+
+    if let Some(task) = guard.take() {
+        task.abort();
+    }
+    Ok(())
+}
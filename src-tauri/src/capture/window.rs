@@ -0,0 +1,114 @@
+// Top-level window discovery and capture, excluding Extab's own windows
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use xcap::Window;
+
+use super::monitor::CapturedFrame;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WindowInfo {
+    pub id: u32,
+    pub title: String,
+    pub app_name: String,
+    // Only reported by xcap on Windows
+    pub pid: Option<u32>,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub is_minimized: bool,
+}
+
+impl From<&Window> for WindowInfo {
+    fn from(window: &Window) -> Self {
+        WindowInfo {
+            id: window.id(),
+            title: window.title().to_string(),
+            app_name: window.app_name().to_string(),
+            pid: window_pid(window),
+            x: window.x(),
+            y: window.y(),
+            width: window.width(),
+            height: window.height(),
+            is_minimized: window.is_minimized(),
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn window_pid(window: &Window) -> Option<u32> {
+    Some(window.process_id())
+}
+
+#[cfg(not(target_os = "windows"))]
+fn window_pid(_window: &Window) -> Option<u32> {
+    None
+}
+
+// Identifies Extab's own windows (the `main` overlay and any other webview).
+// Pid is the reliable signal where xcap exposes it, elsewhere we fall back to
+// the app name and the titles of our webview windows.
+struct OwnWindows {
+    app_name: String,
+    titles: Vec<String>,
+}
+
+impl OwnWindows {
+    fn new(app: &AppHandle) -> Self {
+        let titles = app
+            .webview_windows()
+            .values()
+            .filter_map(|w| w.title().ok())
+            .filter(|t| !t.is_empty())
+            .collect();
+
+        OwnWindows {
+            app_name: app.package_info().name.to_lowercase(),
+            titles,
+        }
+    }
+
+    fn contains(&self, window: &Window) -> bool {
+        if let Some(pid) = window_pid(window) {
+            return pid == std::process::id();
+        }
+
+        window.app_name().to_lowercase() == self.app_name
+            || self.titles.iter().any(|t| t == window.title())
+    }
+}
+
+/// All capturable top-level windows from other applications.
+pub fn other_windows(app: &AppHandle) -> Result<Vec<Window>, String> {
+    let own_windows = OwnWindows::new(app);
+    let windows = Window::all().map_err(|e| format!("Failed to get windows: {}", e))?;
+    Ok(windows
+        .into_iter()
+        .filter(|w| !own_windows.contains(w) && w.width() > 0 && w.height() > 0)
+        .collect())
+}
+
+/// Captures one window as a frame positioned where the window sits on the desktop.
+pub fn capture_window_by_id(app: &AppHandle, window_id: u32) -> Result<CapturedFrame, String> {
+    let window = other_windows(app)?
+        .into_iter()
+        .find(|w| w.id() == window_id)
+        .ok_or(format!("Window {} not found", window_id))?;
+
+    if window.is_minimized() {
+        return Err(format!("Window {} is minimized", window_id));
+    }
+
+    let image = window
+        .capture_image()
+        .map_err(|e| format!("Failed to capture window: {}", e))?;
+    let scale = image.width() as f64 / window.width().max(1) as f64;
+    Ok(CapturedFrame {
+        image,
+        scale,
+        x: window.x(),
+        y: window.y(),
+        monitor: None,
+    })
+}
@@ -0,0 +1,170 @@
+// Side-by-side model comparison
+// Sends one request to several provider/model pairs at once. Each answer
+// streams under its own request id, "<request_id>:<index>", with the usual
+// chat_stream events; `model_comparison_started` maps those ids to models.
+
+use futures_util::future::join_all;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::api::{self, ChatInput};
+use crate::error::ApiError;
+use crate::providers::{self, ChatMessage, ChatOutput, GenerationOptions, ProviderConfig, ReasoningOptions};
+
+// Each answer is a full request, so keep fan-out small
+const MAX_TARGETS: usize = 4;
+
+#[derive(Deserialize, Clone)]
+pub struct ComparisonTarget {
+    // Straight to this provider, otherwise through the Extab backend
+    #[serde(default)]
+    pub provider: Option<ProviderConfig>,
+    // Overrides the provider's model, or picks an Extab model by id or
+    // name. Without either the selected Extab model answers.
+    #[serde(default)]
+    pub model: Option<String>,
+    // Shown instead of "<provider>/<model>"
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+impl ComparisonTarget {
+    fn provider_name(&self) -> &'static str {
+        self.provider.as_ref().map_or("extab", |config| config.kind.name())
+    }
+
+    fn model_name(&self) -> String {
+        self.model
+            .clone()
+            .or_else(|| self.provider.as_ref().map(|config| config.model.clone()))
+            .unwrap_or_else(|| "selected".to_string())
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ComparisonAnswer {
+    pub index: usize,
+    // Request id of this answer's stream events
+    pub request_id: String,
+    pub label: String,
+    pub provider: String,
+    pub model: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ComparisonStarted {
+    pub request_id: String,
+    pub answers: Vec<ComparisonAnswer>,
+}
+
+// Emitted as `model_comparison_result` when each answer ends, and returned
+// together in target order
+#[derive(Debug, Serialize, Clone)]
+pub struct ComparisonResult {
+    #[serde(flatten)]
+    pub answer: ComparisonAnswer,
+    // Includes latency, time to first token and usage
+    pub output: Option<ChatOutput>,
+    pub error: Option<ApiError>,
+}
+
+async fn run_target(
+    app: AppHandle,
+    answer: ComparisonAnswer,
+    target: ComparisonTarget,
+    mut input: ChatInput,
+) -> ComparisonResult {
+    input.generation.model = target.model;
+    let result = match target.provider {
+        Some(config) => providers::run_chat_input(app.clone(), answer.request_id.clone(), config, input).await,
+        None => api::run_chat_input(app.clone(), answer.request_id.clone(), input).await,
+    };
+
+    let (output, error) = match result {
+        Ok(output) => (Some(output), None),
+        Err(error) => (None, Some(error)),
+    };
+    let result = ComparisonResult { answer, output, error };
+    let _ = app.emit("model_comparison_result", result.clone());
+    result
+}
+
+/// Asks every target the same question concurrently, built the same way as
+/// `chat_stream`. Each target picks its own model, `options.model` is not
+/// used. One failing target doesn't stop the others, its error is reported
+/// in its result. `cancel_chat_stream` with `request_id` stops all answers
+/// and the command then fails as cancelled.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn compare_models(
+    app: AppHandle,
+    user_message: String,
+    system_prompt: Option<String>,
+    image_base64: Option<serde_json::Value>,
+    history: Option<Vec<ChatMessage>>,
+    targets: Vec<ComparisonTarget>,
+    reasoning: Option<ReasoningOptions>,
+    options: Option<GenerationOptions>,
+    request_id: Option<String>,
+) -> Result<Vec<ComparisonResult>, ApiError> {
+    if targets.is_empty() || targets.len() > MAX_TARGETS {
+        return Err(ApiError::Config(format!("Compare between 1 and {} models", MAX_TARGETS)));
+    }
+    let request_id = request_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    let input = ChatInput {
+        user_message,
+        system_prompt,
+        image_base64,
+        history: history.unwrap_or_default(),
+        reasoning,
+        generation: options.unwrap_or_default(),
+        ..ChatInput::default()
+    };
+
+    let answers: Vec<ComparisonAnswer> = targets
+        .iter()
+        .enumerate()
+        .map(|(index, target)| {
+            let provider = target.provider_name().to_string();
+            let model = target.model_name();
+            ComparisonAnswer {
+                index,
+                request_id: format!("{}:{}", request_id, index),
+                label: target.label.clone().unwrap_or_else(|| format!("{}/{}", provider, model)),
+                provider,
+                model,
+            }
+        })
+        .collect();
+
+    let runs: Vec<_> = answers
+        .iter()
+        .cloned()
+        .zip(targets)
+        .map(|(answer, target)| run_target(app.clone(), answer, target, input.clone()))
+        .collect();
+
+    // Registered as a whole before any answer starts, so a cancel also
+    // catches answers still resolving their model
+    let streams = app.state::<crate::ChatStreamState>().streams.clone();
+    let task = {
+        let mut streams = streams.lock().unwrap();
+        if streams.contains_key(&request_id) {
+            return Err(ApiError::Config(format!("Comparison {} is already running", request_id)));
+        }
+        let _ = app.emit("model_comparison_started", ComparisonStarted { request_id: request_id.clone(), answers });
+        let task = tokio::spawn(join_all(runs));
+        streams.insert(request_id.clone(), task.abort_handle());
+        task
+    };
+
+    let result = task.await;
+    streams.lock().unwrap().remove(&request_id);
+
+    match result {
+        Ok(results) => Ok(results),
+        Err(e) if e.is_cancelled() => Err(ApiError::Cancelled(format!("Comparison {} was cancelled", request_id))),
+        Err(e) => Err(ApiError::Network(format!("Comparison task failed: {}", e))),
+    }
+}
@@ -22,6 +22,33 @@ pub struct Coordinate {
     pub y: i32,
 }
 
+/// Maps coordinates from a (possibly downscaled) screenshot back to screen
+/// coordinates: `screen = offset + model * scale`.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct CoordinateScale {
+    pub scale_x: f64,
+    pub scale_y: f64,
+    pub offset_x: i32,
+    pub offset_y: i32,
+}
+
+impl CoordinateScale {
+    pub fn to_screen(self, x: i32, y: i32) -> (i32, i32) {
+        (
+            self.offset_x + (x as f64 * self.scale_x).round() as i32,
+            self.offset_y + (y as f64 * self.scale_y).round() as i32,
+        )
+    }
+}
+
+// Coordinates are used as-is when no scale is given
+fn map_point(x: i32, y: i32, scale: Option<CoordinateScale>) -> (i32, i32) {
+    match scale {
+        Some(scale) => scale.to_screen(x, y),
+        None => (x, y),
+    }
+}
+
 // Platform-agnostic interface
 pub trait ComputerControl {
     fn mouse_move(&self, x: i32, y: i32) -> Result<(), String>;
@@ -55,15 +82,22 @@ pub fn get_computer_control() -> Box<dyn ComputerControl + Send + Sync> {
 
 // Tauri commands
 #[tauri::command]
-pub fn computer_mouse_move(x: i32, y: i32) -> Result<String, String> {
+pub fn computer_mouse_move(x: i32, y: i32, scale: Option<CoordinateScale>) -> Result<String, String> {
     let control = get_computer_control();
+    let (x, y) = map_point(x, y, scale);
     control.mouse_move(x, y)?;
     Ok("Mouse moved successfully".to_string())
 }
 
 #[tauri::command]
-pub fn computer_mouse_click(x: i32, y: i32, button: Option<String>) -> Result<String, String> {
+pub fn computer_mouse_click(
+    x: i32,
+    y: i32,
+    button: Option<String>,
+    scale: Option<CoordinateScale>,
+) -> Result<String, String> {
     let control = get_computer_control();
+    let (x, y) = map_point(x, y, scale);
     let mouse_button = match button.as_deref() {
         Some("right") => MouseButton::Right,
         Some("middle") => MouseButton::Middle,
@@ -74,22 +108,39 @@ pub fn computer_mouse_click(x: i32, y: i32, button: Option<String>) -> Result<St
 }
 
 #[tauri::command]
-pub fn computer_mouse_double_click(x: i32, y: i32) -> Result<String, String> {
+pub fn computer_mouse_double_click(x: i32, y: i32, scale: Option<CoordinateScale>) -> Result<String, String> {
     let control = get_computer_control();
+    let (x, y) = map_point(x, y, scale);
     control.mouse_double_click(x, y)?;
     Ok("Mouse double-clicked successfully".to_string())
 }
 
 #[tauri::command]
-pub fn computer_mouse_drag(from_x: i32, from_y: i32, to_x: i32, to_y: i32) -> Result<String, String> {
+pub fn computer_mouse_drag(
+    from_x: i32,
+    from_y: i32,
+    to_x: i32,
+    to_y: i32,
+    scale: Option<CoordinateScale>,
+) -> Result<String, String> {
     let control = get_computer_control();
+    let (from_x, from_y) = map_point(from_x, from_y, scale);
+    let (to_x, to_y) = map_point(to_x, to_y, scale);
     control.mouse_drag(from_x, from_y, to_x, to_y)?;
     Ok("Mouse drag completed successfully".to_string())
 }
 
 #[tauri::command]
-pub fn computer_mouse_scroll(x: i32, y: i32, scroll_x: i32, scroll_y: i32) -> Result<String, String> {
+pub fn computer_mouse_scroll(
+    x: i32,
+    y: i32,
+    scroll_x: i32,
+    scroll_y: i32,
+    scale: Option<CoordinateScale>,
+) -> Result<String, String> {
     let control = get_computer_control();
+    // Only the pointer position is scaled, scroll amounts are in clicks
+    let (x, y) = map_point(x, y, scale);
     control.mouse_scroll(x, y, scroll_x, scroll_y)?;
     Ok("Mouse scroll completed successfully".to_string())
 }
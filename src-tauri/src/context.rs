@@ -0,0 +1,310 @@
+// Conversation context assembly
+// Fits chat history into the model's context window. Tokens are counted
+// locally with the o200k tokenizer; when the history is too long the newest
+// turns are kept and the older ones are folded into a short summary, instead
+// of the request failing on context length.
+
+use once_cell::sync::Lazy;
+use tiktoken_rs::CoreBPE;
+
+use crate::error::ApiError;
+use crate::providers::{ChatMessage, ContentPart, Role};
+
+// Used when the model isn't known or isn't in the table
+const DEFAULT_CONTEXT_WINDOW: usize = 32_000;
+
+// Longest matching prefix wins, like the price table in usage.rs
+const CONTEXT_WINDOWS: &[(&str, usize)] = &[
+    ("gpt-3.5-turbo", 16_385),
+    ("gpt-4", 8_192),
+    ("gpt-4-32k", 32_768),
+    ("gpt-4-turbo", 128_000),
+    ("gpt-4o", 128_000),
+    ("gpt-4.1", 1_047_576),
+    ("gpt-5", 400_000),
+    ("o1", 200_000),
+    ("o3", 200_000),
+    ("o4-mini", 200_000),
+    ("claude", 200_000),
+    ("gemini-1.5", 1_048_576),
+    ("gemini-2", 1_048_576),
+];
+
+// Room kept for the answer when the caller doesn't set a limit
+const DEFAULT_OUTPUT_RESERVE: usize = 4_096;
+// Claude and Gemini tokenize differently, so only part of the window is filled
+const WINDOW_SHARE_PERCENT: usize = 90;
+// Role markers and separators each message adds in chat formats
+const MESSAGE_OVERHEAD: usize = 4;
+// Rough cost of one image, which depends on size and provider
+const IMAGE_TOKENS: usize = 1_000;
+// Upper bound for the summary of trimmed turns
+const SUMMARY_MAX_TOKENS: usize = 1_000;
+// Characters of each trimmed turn quoted in the summary
+const SUMMARY_EXCERPT_CHARS: usize = 240;
+
+static TOKENIZER: Lazy<Option<CoreBPE>> = Lazy::new(|| tiktoken_rs::o200k_base().ok());
+
+pub fn count_tokens(text: &str) -> usize {
+    match TOKENIZER.as_ref() {
+        Some(bpe) => bpe.encode_with_special_tokens(text).len(),
+        // About four characters per token for English text
+        None => text.chars().count().div_ceil(4),
+    }
+}
+
+pub fn message_tokens(message: &ChatMessage) -> usize {
+    let content: usize = message
+        .content
+        .iter()
+        .map(|part| match part {
+            ContentPart::Text { text } => count_tokens(text),
+            ContentPart::Image { .. } => IMAGE_TOKENS,
+            ContentPart::ToolCall(call) => count_tokens(&call.name) + count_tokens(&call.arguments.to_string()),
+            ContentPart::ToolResult(result) => count_tokens(&result.content),
+        })
+        .sum();
+    content + MESSAGE_OVERHEAD
+}
+
+pub fn context_window(model: Option<&str>) -> usize {
+    let Some(model) = model.map(str::to_lowercase) else {
+        return DEFAULT_CONTEXT_WINDOW;
+    };
+    // Provider prefixes like "openai/gpt-4o" from OpenRouter
+    let name = model.rsplit('/').next().unwrap_or(&model);
+
+    CONTEXT_WINDOWS
+        .iter()
+        .filter(|(prefix, _)| name.starts_with(prefix))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map_or(DEFAULT_CONTEXT_WINDOW, |(_, window)| *window)
+}
+
+/// Tokens left for history once the fixed parts of the request (system
+/// prompt, current message) and the answer are accounted for. Fails when
+/// those alone don't fit, the request would be rejected anyway.
+pub fn history_budget(model: Option<&str>, fixed_tokens: usize, max_output_tokens: Option<u32>) -> Result<usize, ApiError> {
+    let usable = context_window(model) * WINDOW_SHARE_PERCENT / 100;
+    let reserve = max_output_tokens.map_or(DEFAULT_OUTPUT_RESERVE, |t| t as usize);
+    usable.checked_sub(fixed_tokens + reserve).ok_or_else(|| {
+        ApiError::Config(format!(
+            "The message needs about {} tokens plus {} for the answer, more than the {} the model's context window allows",
+            fixed_tokens, reserve, usable
+        ))
+    })
+}
+
+/// Keeps the newest turns of `history` (oldest first) that fit in `budget`
+/// tokens. The rest are replaced by a summary leading the result.
+pub fn fit_history(history: Vec<ChatMessage>, budget: usize) -> Vec<ChatMessage> {
+    let tokens: Vec<usize> = history.iter().map(message_tokens).collect();
+    if tokens.iter().sum::<usize>() <= budget {
+        return history;
+    }
+
+    let summary_budget = (budget / 10).min(SUMMARY_MAX_TOKENS);
+    let keep_budget = budget - summary_budget;
+
+    let mut used = 0;
+    let mut split = history.len();
+    while split > 0 && used + tokens[split - 1] <= keep_budget {
+        used += tokens[split - 1];
+        split -= 1;
+    }
+    // Tool results can't lead the history without the call that asked for them
+    while split < history.len() && history[split].role == Role::Tool {
+        split += 1;
+    }
+
+    let mut kept = history;
+    let dropped: Vec<ChatMessage> = kept.drain(..split).collect();
+    let mut messages: Vec<ChatMessage> = summarize(&dropped, summary_budget).into_iter().collect();
+    messages.extend(kept);
+    messages
+}
+
+/// Splits `messages` into the system prompt, the history and the final turn,
+/// then fits the history around the other two.
+pub fn fit_messages(
+    messages: Vec<ChatMessage>,
+    model: Option<&str>,
+    max_output_tokens: Option<u32>,
+) -> Result<Vec<ChatMessage>, ApiError> {
+    let (system, mut conversation): (Vec<ChatMessage>, Vec<ChatMessage>) =
+        messages.into_iter().partition(|m| m.role == Role::System);
+    let Some(last) = conversation.pop() else {
+        return Ok(system);
+    };
+
+    let fixed = system.iter().chain([&last]).map(message_tokens).sum();
+    let budget = history_budget(model, fixed, max_output_tokens)?;
+    let history = fit_history(conversation, budget);
+
+    Ok(system.into_iter().chain(history).chain([last]).collect())
+}
+
+fn excerpt(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match text.char_indices().nth(SUMMARY_EXCERPT_CHARS) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text,
+    }
+}
+
+/// A system message quoting the start of each trimmed turn, newest kept
+/// first when they don't all fit.
+fn summarize(dropped: &[ChatMessage], budget: usize) -> Option<ChatMessage> {
+    let header = format!(
+        "{} earlier messages were left out to fit the context window. They began:",
+        dropped.len()
+    );
+    let mut used = count_tokens(&header) + MESSAGE_OVERHEAD;
+    let mut lines = Vec::new();
+
+    for message in dropped.iter().rev() {
+        let text = message.text_content();
+        if text.trim().is_empty() {
+            continue;
+        }
+        let role = match message.role {
+            Role::System => "System",
+            Role::User => "User",
+            Role::Assistant => "Assistant",
+            Role::Tool => "Tool",
+        };
+        let line = format!("- {}: {}", role, excerpt(&text));
+        let tokens = count_tokens(&line) + 1;
+        if used + tokens > budget {
+            break;
+        }
+        used += tokens;
+        lines.push(line);
+    }
+
+    if lines.is_empty() {
+        return None;
+    }
+    lines.reverse();
+    Some(ChatMessage {
+        role: Role::System,
+        content: vec![ContentPart::Text { text: format!("{}\n{}", header, lines.join("\n")) }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::{ToolCall, ToolResult};
+
+    fn text(role: Role, text: &str) -> ChatMessage {
+        ChatMessage { role, content: vec![ContentPart::Text { text: text.to_string() }] }
+    }
+
+    fn conversation(turns: usize) -> Vec<ChatMessage> {
+        (0..turns)
+            .map(|i| {
+                let role = if i % 2 == 0 { Role::User } else { Role::Assistant };
+                text(role, &format!("Turn {} talks about the weather in some detail for a while.", i))
+            })
+            .collect()
+    }
+
+    fn total_tokens(messages: &[ChatMessage]) -> usize {
+        messages.iter().map(message_tokens).sum()
+    }
+
+    #[test]
+    fn context_window_matches_longest_prefix() {
+        assert_eq!(context_window(Some("gpt-4")), 8_192);
+        assert_eq!(context_window(Some("gpt-4-0613")), 8_192);
+        assert_eq!(context_window(Some("gpt-4-32k")), 32_768);
+        assert_eq!(context_window(Some("gpt-4-turbo-preview")), 128_000);
+        assert_eq!(context_window(Some("GPT-4o-mini")), 128_000);
+        assert_eq!(context_window(Some("openai/gpt-4.1-mini")), 1_047_576);
+        assert_eq!(context_window(Some("llama3")), DEFAULT_CONTEXT_WINDOW);
+        assert_eq!(context_window(None), DEFAULT_CONTEXT_WINDOW);
+    }
+
+    #[test]
+    fn history_budget_subtracts_fixed_parts_and_answer() {
+        let usable = 200_000 * WINDOW_SHARE_PERCENT / 100;
+        assert_eq!(history_budget(Some("claude-sonnet-4"), 1_000, Some(2_000)).unwrap(), usable - 3_000);
+        assert_eq!(history_budget(Some("claude-sonnet-4"), 1_000, None).unwrap(), usable - 1_000 - DEFAULT_OUTPUT_RESERVE);
+    }
+
+    #[test]
+    fn history_budget_rejects_oversized_message() {
+        assert!(matches!(history_budget(Some("gpt-4"), 8_000, None), Err(ApiError::Config(_))));
+        assert!(matches!(
+            fit_messages(vec![text(Role::User, &"word ".repeat(10_000))], Some("gpt-4"), None),
+            Err(ApiError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn fit_history_keeps_history_that_fits() {
+        let history = conversation(6);
+        let budget = total_tokens(&history);
+        assert_eq!(fit_history(history.clone(), budget), history);
+    }
+
+    #[test]
+    fn fit_history_keeps_newest_turns_and_summarizes_the_rest() {
+        let history = conversation(400);
+        let budget = total_tokens(&history) / 2;
+        let fitted = fit_history(history.clone(), budget);
+
+        assert_eq!(fitted[0].role, Role::System);
+        assert!(fitted[0].text_content().contains("earlier messages were left out"));
+        assert_eq!(fitted.last(), history.last());
+        assert!(total_tokens(&fitted) <= budget);
+
+        // The kept turns are the newest ones, in order
+        let kept = &fitted[1..];
+        assert_eq!(kept, &history[history.len() - kept.len()..]);
+    }
+
+    #[test]
+    fn fit_history_never_leads_with_a_tool_result() {
+        let call = ToolCall { id: "call_1".to_string(), name: "lookup".to_string(), arguments: serde_json::json!({ "q": "weather" }) };
+        let result = ToolResult {
+            tool_call_id: "call_1".to_string(),
+            name: "lookup".to_string(),
+            content: "Sunny and warm all week across the whole region.".to_string(),
+            is_error: false,
+        };
+        let mut history = conversation(4);
+        history.push(ChatMessage { role: Role::Assistant, content: vec![ContentPart::ToolCall(call)] });
+        history.push(ChatMessage { role: Role::Tool, content: vec![ContentPart::ToolResult(result)] });
+        history.push(text(Role::Assistant, "It will be sunny."));
+
+        for budget in 0..total_tokens(&history) {
+            let fitted = fit_history(history.clone(), budget);
+            let first = fitted.iter().find(|m| m.role != Role::System);
+            assert!(first.is_none_or(|m| m.role != Role::Tool), "budget {} kept a leading tool result", budget);
+        }
+    }
+
+    #[test]
+    fn summarize_quotes_newest_turns_within_budget() {
+        let mut dropped = conversation(3);
+        dropped.push(text(Role::User, "   "));
+        dropped.push(text(Role::Assistant, &"long ".repeat(200)));
+
+        let summary = summarize(&dropped, SUMMARY_MAX_TOKENS).unwrap().text_content();
+        let lines: Vec<&str> = summary.lines().collect();
+        // Header, then one line per non-empty turn, oldest first
+        assert_eq!(lines.len(), 5);
+        assert!(lines[0].starts_with("5 earlier messages"));
+        assert!(lines[1].starts_with("- User: Turn 0"));
+        assert!(lines[4].starts_with("- Assistant: long"));
+        assert!(lines[4].ends_with('…'));
+
+        // A tight budget keeps only the newest turns
+        let header_tokens = count_tokens(lines[0]) + MESSAGE_OVERHEAD;
+        let tight = summarize(&dropped, header_tokens + count_tokens(lines[4]) + 1).unwrap().text_content();
+        assert_eq!(tight.lines().count(), 2);
+        assert!(summarize(&dropped, header_tokens).is_none());
+    }
+}
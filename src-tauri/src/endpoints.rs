@@ -0,0 +1,469 @@
+// Backend endpoint configuration
+// Every value resolves from the saved settings first, then the process
+// environment, then whatever was compiled in at build time, so switching to a
+// self-hosted or staging backend needs no rebuild.
+
+use reqwest::header::{HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use tauri::{AppHandle, Manager};
+
+use crate::error::ApiError;
+
+const DEFAULT_AUTH_HEADER: &str = "Authorization";
+const DEFAULT_AUTH_SCHEME: &str = "Bearer";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct EndpointSettings {
+    // Base URLs, e.g. "https://api.example.com". Chat, audio and models fall
+    // back to APP_ENDPOINT, payment to PAYMENT_ENDPOINT.
+    pub chat_endpoint: Option<String>,
+    pub audio_endpoint: Option<String>,
+    pub models_endpoint: Option<String>,
+    pub payment_endpoint: Option<String>,
+    // Falls back to API_ACCESS_KEY. Never returned by `get_endpoint_settings`.
+    pub api_access_key: Option<String>,
+    // Header carrying the key, "Authorization" by default
+    pub auth_header: Option<String>,
+    // Prefix before the key, "Bearer" by default, empty for the bare key
+    pub auth_scheme: Option<String>,
+    // Sent with every backend request
+    pub extra_headers: HashMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EndpointSource {
+    Settings,
+    Env,
+    Default,
+    Unset,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ResolvedEndpoint {
+    pub value: Option<String>,
+    pub source: EndpointSource,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct EndpointStatus {
+    pub chat: ResolvedEndpoint,
+    pub audio: ResolvedEndpoint,
+    pub models: ResolvedEndpoint,
+    pub payment: ResolvedEndpoint,
+    pub api_access_key_source: EndpointSource,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct EndpointSettingsResponse {
+    pub settings: EndpointSettings,
+    pub has_api_access_key: bool,
+    pub status: EndpointStatus,
+}
+
+pub struct EndpointState {
+    settings: RwLock<EndpointSettings>,
+}
+
+impl EndpointState {
+    pub fn new(settings: EndpointSettings) -> Self {
+        EndpointState {
+            settings: RwLock::new(settings),
+        }
+    }
+}
+
+fn resolve(setting: Option<&str>, env_name: &str, compiled: Option<&'static str>) -> ResolvedEndpoint {
+    let non_empty = |value: &str| !value.trim().is_empty();
+
+    if let Some(value) = setting.filter(|v| non_empty(v)) {
+        return ResolvedEndpoint { value: Some(value.trim().to_string()), source: EndpointSource::Settings };
+    }
+    if let Some(value) = env::var(env_name).ok().filter(|v| non_empty(v)) {
+        return ResolvedEndpoint { value: Some(value.trim().to_string()), source: EndpointSource::Env };
+    }
+    match compiled.filter(|v| non_empty(v)) {
+        Some(value) => ResolvedEndpoint { value: Some(value.trim().to_string()), source: EndpointSource::Default },
+        None => ResolvedEndpoint { value: None, source: EndpointSource::Unset },
+    }
+}
+
+fn require(resolved: ResolvedEndpoint, env_name: &str) -> Result<String, ApiError> {
+    resolved
+        .value
+        .map(|value| value.trim_end_matches('/').to_string())
+        .ok_or_else(|| {
+            ApiError::Config(format!(
+                "{} is not configured. Set it in the endpoint settings or the environment.",
+                env_name
+            ))
+        })
+}
+
+/// Endpoint settings resolved against the environment, for one request.
+pub struct Endpoints {
+    settings: EndpointSettings,
+}
+
+impl Endpoints {
+    fn chat(&self) -> ResolvedEndpoint {
+        resolve(self.settings.chat_endpoint.as_deref(), "APP_ENDPOINT", option_env!("APP_ENDPOINT"))
+    }
+
+    fn audio(&self) -> ResolvedEndpoint {
+        resolve(self.settings.audio_endpoint.as_deref(), "APP_ENDPOINT", option_env!("APP_ENDPOINT"))
+    }
+
+    fn models(&self) -> ResolvedEndpoint {
+        resolve(self.settings.models_endpoint.as_deref(), "APP_ENDPOINT", option_env!("APP_ENDPOINT"))
+    }
+
+    fn payment(&self) -> ResolvedEndpoint {
+        resolve(self.settings.payment_endpoint.as_deref(), "PAYMENT_ENDPOINT", option_env!("PAYMENT_ENDPOINT"))
+    }
+
+    fn api_access_key(&self) -> ResolvedEndpoint {
+        resolve(self.settings.api_access_key.as_deref(), "API_ACCESS_KEY", option_env!("API_ACCESS_KEY"))
+    }
+
+    pub fn chat_base(&self) -> Result<String, ApiError> {
+        require(self.chat(), "APP_ENDPOINT")
+    }
+
+    pub fn audio_base(&self) -> Result<String, ApiError> {
+        require(self.audio(), "APP_ENDPOINT")
+    }
+
+    pub fn models_base(&self) -> Result<String, ApiError> {
+        require(self.models(), "APP_ENDPOINT")
+    }
+
+    pub fn payment_base(&self) -> Result<String, ApiError> {
+        require(self.payment(), "PAYMENT_ENDPOINT")
+    }
+
+    /// Checks that an access key is configured, so callers fail before sending.
+    pub fn check_auth(&self) -> Result<(), ApiError> {
+        require(self.api_access_key(), "API_ACCESS_KEY").map(|_| ())
+    }
+
+    /// Adds the access key and any extra headers to a backend request.
+    pub fn authorize(&self, builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        let header = self.settings.auth_header.as_deref().unwrap_or(DEFAULT_AUTH_HEADER);
+        let scheme = self.settings.auth_scheme.as_deref().unwrap_or(DEFAULT_AUTH_SCHEME).trim();
+
+        let mut builder = builder;
+        if let Some(key) = self.api_access_key().value {
+            let value = if scheme.is_empty() { key } else { format!("{} {}", scheme, key) };
+            builder = builder.header(header, value);
+        }
+
+        self.settings
+            .extra_headers
+            .iter()
+            .fold(builder, |builder, (name, value)| builder.header(name, value))
+    }
+
+    fn status(&self) -> EndpointStatus {
+        EndpointStatus {
+            chat: self.chat(),
+            audio: self.audio(),
+            models: self.models(),
+            payment: self.payment(),
+            api_access_key_source: self.api_access_key().source,
+        }
+    }
+}
+
+pub fn endpoints(app: &AppHandle) -> Endpoints {
+    Endpoints {
+        settings: app.state::<EndpointState>().settings.read().unwrap().clone(),
+    }
+}
+
+fn validate_url(name: &str, value: &Option<String>) -> Result<Option<String>, ApiError> {
+    let Some(value) = value.as_deref().map(str::trim).filter(|v| !v.is_empty()) else {
+        return Ok(None);
+    };
+
+    let url = reqwest::Url::parse(value).map_err(|e| ApiError::Config(format!("Invalid {} URL: {}", name, e)))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(ApiError::Config(format!("{} URL must use http or https", name)));
+    }
+    if url.query().is_some() || url.fragment().is_some() {
+        return Err(ApiError::Config(format!("{} URL must be a base URL without a query", name)));
+    }
+
+    Ok(Some(value.trim_end_matches('/').to_string()))
+}
+
+fn validate_header(name: &str, value: &str) -> Result<(), ApiError> {
+    HeaderName::from_bytes(name.as_bytes())
+        .map_err(|_| ApiError::Config(format!("Invalid header name: {}", name)))?;
+    HeaderValue::from_str(value).map_err(|_| ApiError::Config(format!("Invalid value for header {}", name)))?;
+    Ok(())
+}
+
+// An invalid field is dropped so the rest of the settings still apply
+fn keep_valid<T>(result: Result<Option<T>, ApiError>, errors: &mut Vec<ApiError>) -> Option<T> {
+    result.unwrap_or_else(|e| {
+        errors.push(e);
+        None
+    })
+}
+
+/// Normalizes settings and drops each field that can't be used, along with
+/// the reason it was dropped.
+fn sanitize(mut settings: EndpointSettings) -> (EndpointSettings, Vec<ApiError>) {
+    let mut errors = Vec::new();
+    settings.chat_endpoint = keep_valid(validate_url("chat", &settings.chat_endpoint), &mut errors);
+    settings.audio_endpoint = keep_valid(validate_url("audio", &settings.audio_endpoint), &mut errors);
+    settings.models_endpoint = keep_valid(validate_url("models", &settings.models_endpoint), &mut errors);
+    settings.payment_endpoint = keep_valid(validate_url("payment", &settings.payment_endpoint), &mut errors);
+
+    if settings.auth_scheme.as_deref().is_some_and(|scheme| HeaderValue::from_str(scheme).is_err()) {
+        errors.push(ApiError::Config("Invalid auth scheme".to_string()));
+        settings.auth_scheme = None;
+    }
+    // The key is sent as a header value whichever header carries it
+    if let Some(key) = settings.api_access_key.as_deref() {
+        let scheme = settings.auth_scheme.as_deref().unwrap_or(DEFAULT_AUTH_SCHEME).trim();
+        let value = if scheme.is_empty() { key.to_string() } else { format!("{} {}", scheme, key) };
+        if HeaderValue::from_str(&value).is_err() {
+            errors.push(ApiError::Config("Invalid API access key, it can't be sent in a header".to_string()));
+            settings.api_access_key = None;
+        }
+    }
+
+    // Header names are stored trimmed, as they're sent
+    settings.auth_header = settings.auth_header.map(|h| h.trim().to_string()).filter(|h| !h.is_empty());
+    if let Some(header) = settings.auth_header.as_deref() {
+        if HeaderName::from_bytes(header.as_bytes()).is_err() {
+            errors.push(ApiError::Config(format!("Invalid header name: {}", header)));
+            settings.auth_header = None;
+        }
+    }
+    settings.extra_headers = settings
+        .extra_headers
+        .into_iter()
+        .map(|(name, value)| (name.trim().to_string(), value))
+        .filter(|(name, value)| match validate_header(name, value) {
+            Ok(()) => true,
+            Err(e) => {
+                errors.push(e);
+                false
+            }
+        })
+        .collect();
+
+    (settings, errors)
+}
+
+/// Normalizes and validates settings before they're saved.
+fn validate(settings: EndpointSettings) -> Result<EndpointSettings, ApiError> {
+    let (settings, errors) = sanitize(settings);
+    match errors.into_iter().next() {
+        Some(e) => Err(e),
+        None => Ok(settings),
+    }
+}
+
+fn get_settings_path(app: &AppHandle) -> Result<PathBuf, ApiError> {
+    let app_data_dir = app.path().app_data_dir()
+        .map_err(|e| ApiError::Storage(format!("Failed to get app data directory: {}", e)))?;
+
+    fs::create_dir_all(&app_data_dir)
+        .map_err(|e| ApiError::Storage(format!("Failed to create app data directory: {}", e)))?;
+
+    Ok(app_data_dir.join("endpoint_settings.json"))
+}
+
+/// Reads the saved settings, defaults when none are saved or they can't be
+/// parsed. They're normalized again, older saves kept untrimmed header names,
+/// and a field that no longer validates is dropped without losing the rest.
+pub fn load_settings(app: &AppHandle) -> EndpointSettings {
+    let Some(settings) = get_settings_path(app)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+    else {
+        return EndpointSettings::default();
+    };
+
+    let (settings, errors) = sanitize(settings);
+    for e in errors {
+        eprintln!("Ignoring saved endpoint setting: {}", e);
+    }
+    settings
+}
+
+/// Saved settings with the access key withheld, plus where each value
+/// currently resolves from.
+#[tauri::command]
+pub fn get_endpoint_settings(app: AppHandle) -> EndpointSettingsResponse {
+    let endpoints = endpoints(&app);
+    let mut settings = endpoints.settings.clone();
+    let has_api_access_key = settings.api_access_key.take().is_some_and(|k| !k.is_empty());
+
+    EndpointSettingsResponse {
+        settings,
+        has_api_access_key,
+        status: endpoints.status(),
+    }
+}
+
+/// Validates, saves and applies endpoint settings. Leave `api_access_key`
+/// unset to keep the saved key, or send an empty string to clear it.
+#[tauri::command]
+pub fn set_endpoint_settings(app: AppHandle, mut settings: EndpointSettings) -> Result<EndpointStatus, ApiError> {
+    let state = app.state::<EndpointState>();
+    match settings.api_access_key.as_deref() {
+        None => settings.api_access_key = state.settings.read().unwrap().api_access_key.clone(),
+        Some(key) if key.trim().is_empty() => settings.api_access_key = None,
+        Some(_) => {}
+    }
+    let settings = validate(settings)?;
+
+    let content = serde_json::to_string(&settings)
+        .map_err(|e| ApiError::Storage(format!("Failed to serialize endpoint settings: {}", e)))?;
+    fs::write(get_settings_path(&app)?, content)
+        .map_err(|e| ApiError::Storage(format!("Failed to write endpoint settings: {}", e)))?;
+
+    *state.settings.write().unwrap() = settings;
+    Ok(endpoints(&app).status())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header_settings(auth_header: &str, extra: &[(&str, &str)]) -> EndpointSettings {
+        EndpointSettings {
+            api_access_key: Some("secret".to_string()),
+            auth_header: Some(auth_header.to_string()),
+            extra_headers: extra.iter().map(|(n, v)| (n.to_string(), v.to_string())).collect(),
+            ..EndpointSettings::default()
+        }
+    }
+
+    #[test]
+    fn resolve_prefers_settings_then_env_then_compiled() {
+        let env_name = "ENDPOINTS_TEST_RESOLVE";
+        env::set_var(env_name, " https://env.example.com ");
+
+        let resolved = resolve(Some(" https://saved.example.com "), env_name, Some("https://built.example.com"));
+        assert_eq!(resolved.value.as_deref(), Some("https://saved.example.com"));
+        assert_eq!(resolved.source, EndpointSource::Settings);
+
+        // A blank setting doesn't shadow the environment
+        let resolved = resolve(Some("  "), env_name, Some("https://built.example.com"));
+        assert_eq!(resolved.value.as_deref(), Some("https://env.example.com"));
+        assert_eq!(resolved.source, EndpointSource::Env);
+
+        env::remove_var(env_name);
+        let resolved = resolve(None, env_name, Some("https://built.example.com"));
+        assert_eq!(resolved.value.as_deref(), Some("https://built.example.com"));
+        assert_eq!(resolved.source, EndpointSource::Default);
+
+        let resolved = resolve(None, env_name, Some(""));
+        assert_eq!(resolved.value, None);
+        assert_eq!(resolved.source, EndpointSource::Unset);
+    }
+
+    #[test]
+    fn require_strips_trailing_slash_and_reports_unset() {
+        let resolved = ResolvedEndpoint { value: Some("https://api.example.com/".to_string()), source: EndpointSource::Settings };
+        assert_eq!(require(resolved, "APP_ENDPOINT").unwrap(), "https://api.example.com");
+
+        let unset = ResolvedEndpoint { value: None, source: EndpointSource::Unset };
+        assert!(matches!(require(unset, "APP_ENDPOINT"), Err(ApiError::Config(_))));
+    }
+
+    #[test]
+    fn validate_url_normalizes_and_rejects() {
+        let url = |value: &str| validate_url("chat", &Some(value.to_string()));
+        assert_eq!(url(" https://api.example.com/v1/ ").unwrap().as_deref(), Some("https://api.example.com/v1"));
+        assert_eq!(url("   ").unwrap(), None);
+        assert!(url("ftp://api.example.com").is_err());
+        assert!(url("https://api.example.com?key=1").is_err());
+        assert!(url("not a url").is_err());
+    }
+
+    #[test]
+    fn validate_stores_trimmed_header_names() {
+        let settings = validate(header_settings(" X-Team ", &[(" X-Org ", "acme")])).unwrap();
+        assert_eq!(settings.auth_header.as_deref(), Some("X-Team"));
+        assert_eq!(settings.extra_headers.get("X-Org").map(String::as_str), Some("acme"));
+
+        let settings = validate(header_settings("  ", &[])).unwrap();
+        assert_eq!(settings.auth_header, None);
+    }
+
+    #[test]
+    fn validate_rejects_bad_headers() {
+        assert!(matches!(validate(header_settings("X Team", &[])), Err(ApiError::Config(_))));
+        assert!(matches!(validate(header_settings("X-Team", &[("X-Org", "line\nbreak")])), Err(ApiError::Config(_))));
+    }
+
+    #[test]
+    fn validate_checks_the_key_for_the_default_header() {
+        let settings = EndpointSettings { api_access_key: Some("line\nbreak".to_string()), ..EndpointSettings::default() };
+        assert!(matches!(validate(settings), Err(ApiError::Config(_))));
+
+        let settings = EndpointSettings { api_access_key: Some("secret".to_string()), ..EndpointSettings::default() };
+        assert_eq!(validate(settings).unwrap().api_access_key.as_deref(), Some("secret"));
+    }
+
+    #[test]
+    fn sanitize_drops_only_invalid_fields() {
+        let settings = EndpointSettings {
+            chat_endpoint: Some("not a url".to_string()),
+            audio_endpoint: Some("https://audio.example.com/".to_string()),
+            ..header_settings("X Team", &[("X-Org", "acme"), ("X-Bad", "line\nbreak")])
+        };
+        let (settings, errors) = sanitize(settings);
+
+        assert_eq!(errors.len(), 3);
+        assert_eq!(settings.chat_endpoint, None);
+        assert_eq!(settings.audio_endpoint.as_deref(), Some("https://audio.example.com"));
+        assert_eq!(settings.api_access_key.as_deref(), Some("secret"));
+        assert_eq!(settings.auth_header, None);
+        assert_eq!(settings.extra_headers.len(), 1);
+        assert_eq!(settings.extra_headers.get("X-Org").map(String::as_str), Some("acme"));
+    }
+
+    #[test]
+    fn sanitize_drops_a_key_that_cant_be_sent() {
+        let settings = EndpointSettings {
+            api_access_key: Some("se\ncret".to_string()),
+            auth_scheme: Some("Tok\u{7f}en".to_string()),
+            chat_endpoint: Some("https://api.example.com".to_string()),
+            ..EndpointSettings::default()
+        };
+        let (settings, errors) = sanitize(settings);
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(settings.auth_scheme, None);
+        assert_eq!(settings.api_access_key, None);
+        assert_eq!(settings.chat_endpoint.as_deref(), Some("https://api.example.com"));
+    }
+
+    #[test]
+    fn authorize_applies_saved_headers() {
+        let settings = validate(header_settings(" X-Team ", &[(" X-Org ", "acme")])).unwrap();
+        let endpoints = Endpoints { settings };
+        let request = endpoints
+            .authorize(reqwest::Client::new().get("https://api.example.com"))
+            .build()
+            .unwrap();
+
+        assert_eq!(request.headers()["x-team"], "Bearer secret");
+        assert_eq!(request.headers()["x-org"], "acme");
+    }
+}
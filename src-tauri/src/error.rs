@@ -0,0 +1,235 @@
+// Error type shared by the backend API commands
+// Serializes to {code, http_status, retryable, message} so the frontend can
+// branch on the kind of failure instead of matching message text.
+
+use serde::{Serialize, Serializer};
+use std::fmt;
+
+#[derive(Debug, Clone)]
+pub enum ApiError {
+    // Connection failures, DNS, TLS
+    Network(String),
+    // No response in time, server timeouts (408, 504) are Server errors
+    Timeout(String),
+    // Rejected API access key
+    Auth { status: Option<u16>, message: String },
+    // Missing, invalid or expired license
+    License { status: Option<u16>, message: String },
+    // Rate limits and exhausted usage
+    Quota { status: Option<u16>, message: String },
+    // Any other non-success response
+    Server { status: u16, message: String },
+    // The stream failed after it started, resending would repeat the output
+    Stream(String),
+    // Response body didn't have the expected shape
+    Parse(String),
+    // Missing endpoint or key configuration
+    Config(String),
+    // Local app data could not be read or written
+    Storage(String),
+    // Stopped by the user before it finished
+    Cancelled(String),
+    // Model output didn't match the requested schema
+    Validation(String),
+}
+
+#[derive(Serialize)]
+struct ApiErrorPayload<'a> {
+    code: &'static str,
+    http_status: Option<u16>,
+    retryable: bool,
+    message: &'a str,
+}
+
+impl ApiError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::Network(_) => "network",
+            ApiError::Timeout(_) => "timeout",
+            ApiError::Auth { .. } => "auth",
+            ApiError::License { .. } => "license",
+            ApiError::Quota { .. } => "quota",
+            ApiError::Server { .. } => "server",
+            ApiError::Stream(_) => "stream",
+            ApiError::Parse(_) => "parse",
+            ApiError::Config(_) => "config",
+            ApiError::Storage(_) => "storage",
+            ApiError::Cancelled(_) => "cancelled",
+            ApiError::Validation(_) => "validation",
+        }
+    }
+
+    pub fn http_status(&self) -> Option<u16> {
+        match self {
+            ApiError::Auth { status, .. }
+            | ApiError::License { status, .. }
+            | ApiError::Quota { status, .. } => *status,
+            ApiError::Server { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            ApiError::Network(message)
+            | ApiError::Timeout(message)
+            | ApiError::Stream(message)
+            | ApiError::Parse(message)
+            | ApiError::Config(message)
+            | ApiError::Storage(message)
+            | ApiError::Cancelled(message)
+            | ApiError::Validation(message) => message,
+            ApiError::Auth { message, .. }
+            | ApiError::License { message, .. }
+            | ApiError::Quota { message, .. }
+            | ApiError::Server { message, .. } => message,
+        }
+    }
+
+    /// Whether the same request may succeed if sent again later.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ApiError::Network(_) | ApiError::Timeout(_) => true,
+            ApiError::Quota { status, .. } => *status == Some(429),
+            ApiError::Server { status, .. } => *status >= 500 || *status == 408,
+            _ => false,
+        }
+    }
+
+    /// Maps a transport error, keeping the URL (and any query secrets) out of the message.
+    pub fn from_reqwest(context: &str, error: reqwest::Error) -> Self {
+        let error = error.without_url();
+        let message = format!("Failed to make {} request: {}", context, error);
+
+        if error.is_timeout() {
+            ApiError::Timeout(message)
+        } else if error.is_decode() {
+            ApiError::Parse(format!("Failed to parse {} response: {}", context, error))
+        } else {
+            ApiError::Network(message)
+        }
+    }
+
+    /// Maps a non-success response, preferring the server's `error` or
+    /// `message` field over the raw body.
+    pub fn from_response(status: reqwest::StatusCode, body: &str) -> Self {
+        let detail = serde_json::from_str::<serde_json::Value>(body)
+            .ok()
+            .and_then(|json| {
+                json.get("error")
+                    .and_then(|e| e.as_str().or_else(|| e.get("message").and_then(|m| m.as_str())))
+                    .or_else(|| json.get("message").and_then(|m| m.as_str()))
+                    .map(|s| s.to_string())
+            })
+            .unwrap_or_else(|| body.to_string());
+
+        let message = format!("Server error ({}): {}", status, detail);
+        let code = status.as_u16();
+
+        match code {
+            401 => ApiError::Auth { status: Some(code), message },
+            402 | 403 => ApiError::License { status: Some(code), message },
+            429 => ApiError::Quota { status: Some(code), message },
+            _ => ApiError::Server { status: code, message },
+        }
+    }
+
+    /// Checks the response status, consuming the body into an error on failure.
+    pub async fn check_response(response: reqwest::Response) -> Result<reqwest::Response, ApiError> {
+        if response.status().is_success() {
+            return Ok(response);
+        }
+
+        let status = response.status();
+        let body = response.text().await.unwrap_or_else(|_| "Unknown server error".to_string());
+        Err(ApiError::from_response(status, &body))
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl Serialize for ApiError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ApiErrorPayload {
+            code: self.code(),
+            http_status: self.http_status(),
+            retryable: self.is_retryable(),
+            message: self.message(),
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::StatusCode;
+
+    fn from_status(code: u16, body: &str) -> ApiError {
+        ApiError::from_response(StatusCode::from_u16(code).unwrap(), body)
+    }
+
+    #[test]
+    fn classifies_status_codes() {
+        assert_eq!(from_status(401, "").code(), "auth");
+        assert_eq!(from_status(402, "").code(), "license");
+        assert_eq!(from_status(403, "").code(), "license");
+        assert_eq!(from_status(429, "").code(), "quota");
+        for code in [400, 404, 408, 500, 503, 504] {
+            let error = from_status(code, "");
+            assert_eq!(error.code(), "server");
+            assert_eq!(error.http_status(), Some(code));
+        }
+    }
+
+    #[test]
+    fn retries_only_transient_failures() {
+        for code in [408, 429, 500, 502, 503, 504] {
+            assert!(from_status(code, "").is_retryable(), "{} should be retryable", code);
+        }
+        for code in [400, 401, 402, 403, 404, 422] {
+            assert!(!from_status(code, "").is_retryable(), "{} should not be retryable", code);
+        }
+        assert!(ApiError::Network("reset".to_string()).is_retryable());
+        assert!(ApiError::Timeout("slow".to_string()).is_retryable());
+        assert!(!ApiError::Quota { status: None, message: "used up".to_string() }.is_retryable());
+        assert!(!ApiError::Stream("cut off".to_string()).is_retryable());
+        assert!(!ApiError::Config("no endpoint".to_string()).is_retryable());
+    }
+
+    #[test]
+    fn prefers_the_server_message() {
+        let nested = from_status(500, r#"{"error": {"message": "model overloaded"}}"#);
+        assert_eq!(nested.message(), "Server error (500 Internal Server Error): model overloaded");
+        let flat = from_status(400, r#"{"message": "bad input"}"#);
+        assert!(flat.message().ends_with(": bad input"));
+        let plain = from_status(502, "upstream down");
+        assert!(plain.message().ends_with(": upstream down"));
+    }
+
+    #[test]
+    fn serializes_for_the_frontend() {
+        let json = serde_json::to_value(from_status(503, "busy")).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "code": "server",
+                "http_status": 503,
+                "retryable": true,
+                "message": "Server error (503 Service Unavailable): busy",
+            })
+        );
+
+        let json = serde_json::to_value(ApiError::Cancelled("stopped".to_string())).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "code": "cancelled", "http_status": null, "retryable": false, "message": "stopped" })
+        );
+    }
+}
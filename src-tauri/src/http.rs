@@ -0,0 +1,167 @@
+// Shared HTTP client for backend API calls
+// One client is kept in Tauri state so connections are reused. It is rebuilt
+// whenever the network settings change, no restart or rebuild needed.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::error::ApiError;
+use crate::retry::RetryPolicy;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct HttpSettings {
+    pub connect_timeout_secs: u64,
+    // Maximum gap between received bytes, so stalled streams fail instead of hanging
+    pub read_timeout_secs: u64,
+    // Honour HTTP_PROXY / HTTPS_PROXY / NO_PROXY from the environment
+    pub use_env_proxy: bool,
+    // Explicit proxy for all requests, takes precedence over the environment
+    pub proxy_url: Option<String>,
+    // Comma separated hosts that bypass `proxy_url`
+    pub no_proxy: Option<String>,
+    // Extra trusted roots, for corporate TLS interception. Each entry is
+    // either PEM text or a path to a PEM file.
+    pub extra_root_certificates: Vec<String>,
+    // Backoff for transient failures on the chat, audio and models calls
+    pub retry: RetryPolicy,
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        HttpSettings {
+            connect_timeout_secs: 10,
+            read_timeout_secs: 60,
+            use_env_proxy: true,
+            proxy_url: None,
+            no_proxy: None,
+            extra_root_certificates: Vec::new(),
+            retry: RetryPolicy::default(),
+        }
+    }
+}
+
+pub struct HttpClientState {
+    settings: RwLock<HttpSettings>,
+    client: RwLock<reqwest::Client>,
+}
+
+impl HttpClientState {
+    pub fn new(settings: HttpSettings) -> Self {
+        // Fall back to defaults rather than failing startup on bad saved
+        // settings, and report the defaults since those are in effect
+        let (settings, client) = match build_client(&settings) {
+            Ok(client) => (settings, client),
+            Err(e) => {
+                eprintln!("Invalid HTTP settings, using defaults: {}", e);
+                let defaults = HttpSettings::default();
+                let client = build_client(&defaults).unwrap_or_default();
+                (defaults, client)
+            }
+        };
+
+        HttpClientState {
+            settings: RwLock::new(settings),
+            client: RwLock::new(client),
+        }
+    }
+
+    // reqwest::Client is reference counted, cloning shares the connection pool
+    pub fn client(&self) -> reqwest::Client {
+        self.client.read().unwrap().clone()
+    }
+
+    pub fn settings(&self) -> HttpSettings {
+        self.settings.read().unwrap().clone()
+    }
+
+    fn replace(&self, settings: HttpSettings, client: reqwest::Client) {
+        *self.settings.write().unwrap() = settings;
+        *self.client.write().unwrap() = client;
+    }
+}
+
+fn load_certificates(entry: &str) -> Result<Vec<reqwest::Certificate>, ApiError> {
+    let pem = if entry.contains("-----BEGIN") {
+        entry.as_bytes().to_vec()
+    } else {
+        fs::read(entry).map_err(|e| ApiError::Config(format!("Failed to read certificate {}: {}", entry, e)))?
+    };
+
+    reqwest::Certificate::from_pem_bundle(&pem)
+        .map_err(|e| ApiError::Config(format!("Invalid certificate {}: {}", entry, e)))
+}
+
+pub fn build_client(settings: &HttpSettings) -> Result<reqwest::Client, ApiError> {
+    let mut builder = reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(settings.connect_timeout_secs.max(1)))
+        .read_timeout(Duration::from_secs(settings.read_timeout_secs.max(1)));
+
+    if !settings.use_env_proxy {
+        builder = builder.no_proxy();
+    }
+
+    if let Some(proxy_url) = settings.proxy_url.as_deref().filter(|p| !p.trim().is_empty()) {
+        let proxy = reqwest::Proxy::all(proxy_url.trim())
+            .map_err(|e| ApiError::Config(format!("Invalid proxy URL: {}", e)))?
+            .no_proxy(settings.no_proxy.as_deref().and_then(reqwest::NoProxy::from_string));
+        builder = builder.proxy(proxy);
+    }
+
+    for entry in &settings.extra_root_certificates {
+        for certificate in load_certificates(entry)? {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+
+    builder
+        .build()
+        .map_err(|e| ApiError::Config(format!("Failed to build HTTP client: {}", e)))
+}
+
+fn get_settings_path(app: &AppHandle) -> Result<PathBuf, ApiError> {
+    let app_data_dir = app.path().app_data_dir()
+        .map_err(|e| ApiError::Storage(format!("Failed to get app data directory: {}", e)))?;
+
+    fs::create_dir_all(&app_data_dir)
+        .map_err(|e| ApiError::Storage(format!("Failed to create app data directory: {}", e)))?;
+
+    Ok(app_data_dir.join("http_settings.json"))
+}
+
+/// Reads the saved settings, defaults when none are saved or they can't be parsed.
+pub fn load_settings(app: &AppHandle) -> HttpSettings {
+    get_settings_path(app)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Shortcut for commands that only need the client.
+pub fn client(app: &AppHandle) -> reqwest::Client {
+    app.state::<HttpClientState>().client()
+}
+
+#[tauri::command]
+pub fn get_http_settings(app: AppHandle) -> HttpSettings {
+    app.state::<HttpClientState>().settings()
+}
+
+/// Validates the settings by building a client before saving and applying them.
+#[tauri::command]
+pub fn set_http_settings(app: AppHandle, settings: HttpSettings) -> Result<(), ApiError> {
+    let client = build_client(&settings)?;
+
+    let content = serde_json::to_string(&settings)
+        .map_err(|e| ApiError::Storage(format!("Failed to serialize HTTP settings: {}", e)))?;
+    fs::write(get_settings_path(&app)?, content)
+        .map_err(|e| ApiError::Storage(format!("Failed to write HTTP settings: {}", e)))?;
+
+    app.state::<HttpClientState>().replace(settings, client);
+    Ok(())
+}
@@ -3,17 +3,29 @@ mod window;
 mod shortcuts;
 mod activate;
 mod api;
+mod compare;
+mod context;
+mod endpoints;
+mod error;
+mod http;
+mod model_cache;
+mod retry;
+mod schema;
+mod sse;
+mod structured;
+mod usage;
 mod computer_use;
+mod capture;
+mod ocr;
+mod providers;
 
 #[cfg(target_os = "macos")]
 use tauri_plugin_macos_permissions;
-use xcap::Monitor;
-use base64::Engine;
-use image::codecs::jpeg::JpegEncoder;
-use image::{DynamicImage, ImageBuffer, Rgba};
 use tauri_plugin_http;
 
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
+use tauri::Manager;
 use tokio::task::JoinHandle;
 
 mod speaker;
@@ -23,6 +35,37 @@ pub struct AudioState {
     stream_task: Arc<Mutex<Option<JoinHandle<()>>>>,
 }
 
+#[derive(Default)]
+pub struct ScreenWatchState {
+    watch_task: Arc<Mutex<Option<JoinHandle<()>>>>,
+}
+
+#[derive(Default)]
+pub struct ScreenRecorderState {
+    record_task: Arc<Mutex<Option<JoinHandle<()>>>>,
+    frames: capture::FrameBuffer,
+}
+
+// Serializes read-modify-write cycles on the capture history index
+#[derive(Default)]
+pub struct CaptureStoreState {
+    index_lock: Mutex<()>,
+}
+
+// In-flight chat streams by request id, so they can be cancelled, and
+// conversations waiting on tool results
+#[derive(Default)]
+pub struct ChatStreamState {
+    streams: Arc<Mutex<HashMap<String, tokio::task::AbortHandle>>>,
+    tool_rounds: Arc<Mutex<HashMap<String, providers::PendingToolRound>>>,
+}
+
+// Usage records made while a session is active are tagged with its id
+#[derive(Default)]
+pub struct UsageState {
+    session_id: Mutex<Option<String>>,
+}
+
 #[tauri::command]
 fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
@@ -50,75 +93,15 @@ fn set_window_height(window: tauri::WebviewWindow, height: u32) -> Result<(), St
     }
 }
 
-#[tauri::command]
-fn capture_to_base64() -> Result<String, String> {
-    let monitors = Monitor::all().map_err(|e| format!("Failed to get monitors: {}", e))?;
-    let primary_monitor = monitors
-        .into_iter()
-        .find(|m| m.is_primary())
-        .ok_or("No primary monitor found".to_string())?;
-
-    let captured_image = primary_monitor.capture_image().map_err(|e| format!("Failed to capture image: {}", e))?;
-
-    // Convert to DynamicImage
-    let img_buffer = ImageBuffer::<Rgba<u8>, Vec<u8>>::from_raw(
-        captured_image.width(),
-        captured_image.height(),
-        captured_image.as_raw().to_vec(),
-    ).ok_or("Failed to create image buffer".to_string())?;
-
-    let dynamic_image = DynamicImage::ImageRgba8(img_buffer);
-
-    // Convert to RGB (JPEG doesn't support alpha)
-    let rgb_image = dynamic_image.to_rgb8();
-
-    // Try different quality levels to stay under 5MB
-    // Start with quality 85, then reduce if needed
-    for quality in [85, 75, 65, 55, 45, 35].iter() {
-        let mut jpeg_buffer = Vec::new();
-        let mut encoder = JpegEncoder::new_with_quality(&mut jpeg_buffer, *quality);
-        encoder.encode(
-            rgb_image.as_raw(),
-            rgb_image.width(),
-            rgb_image.height(),
-            image::ExtendedColorType::Rgb8,
-        ).map_err(|e| format!("Failed to encode to JPEG: {}", e))?;
-
-        // Check if under 5MB (leave some margin: 4.8MB)
-        if jpeg_buffer.len() <= 5_000_000 {
-            let base64_str = base64::engine::general_purpose::STANDARD.encode(jpeg_buffer);
-            return Ok(base64_str);
-        }
-    }
-
-    // If still too large even at quality 35, resize down and try again
-    let scale_factor = 0.8; // 80% of original size
-    let new_width = (rgb_image.width() as f64 * scale_factor) as u32;
-    let new_height = (rgb_image.height() as f64 * scale_factor) as u32;
-
-    let resized = DynamicImage::ImageRgb8(rgb_image).resize_exact(
-        new_width,
-        new_height,
-        image::imageops::FilterType::Lanczos3
-    ).to_rgb8();
-
-    let mut jpeg_buffer = Vec::new();
-    let mut encoder = JpegEncoder::new_with_quality(&mut jpeg_buffer, 60);
-    encoder.encode(
-        resized.as_raw(),
-        resized.width(),
-        resized.height(),
-        image::ExtendedColorType::Rgb8,
-    ).map_err(|e| format!("Failed to encode to JPEG: {}", e))?;
-
-    let base64_str = base64::engine::general_purpose::STANDARD.encode(jpeg_buffer);
-    Ok(base64_str)
-}
-
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     let mut builder = tauri::Builder::default()
         .manage(AudioState::default())
+        .manage(ScreenWatchState::default())
+        .manage(ScreenRecorderState::default())
+        .manage(CaptureStoreState::default())
+        .manage(ChatStreamState::default())
+        .manage(UsageState::default())
         .manage(shortcuts::WindowVisibility(Mutex::new(false)))
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
@@ -130,7 +113,27 @@ pub fn run() {
             greet,
             get_app_version,
             set_window_height,
-            capture_to_base64,
+            capture::capture_to_base64,
+            capture::capture_screenshot,
+            capture::capture_for_model,
+            capture::capture_redacted,
+            capture::get_redaction_settings,
+            capture::set_redaction_settings,
+            capture::list_monitors,
+            capture::capture_region,
+            capture::list_windows,
+            capture::capture_window,
+            capture::start_screen_watch,
+            capture::stop_screen_watch,
+            capture::list_captures,
+            capture::get_capture,
+            capture::delete_capture,
+            capture::evict_captures,
+            capture::start_screen_recording,
+            capture::stop_screen_recording,
+            capture::export_screen_recording,
+            ocr::ocr_image,
+            ocr::ocr_screen,
             shortcuts::get_shortcuts,
             shortcuts::check_shortcuts_registered,
             shortcuts::set_app_icon_visibility,
@@ -143,8 +146,22 @@ pub fn run() {
             activate::secure_storage_remove,
             api::transcribe_audio,
             api::chat_stream,
+            structured::structured_output,
+            compare::compare_models,
+            api::cancel_chat_stream,
             api::fetch_models,
             api::check_license_status,
+            providers::provider_chat_stream,
+            providers::submit_tool_results,
+            usage::start_usage_session,
+            usage::end_usage_session,
+            usage::get_daily_usage,
+            usage::get_weekly_usage,
+            usage::get_session_usage,
+            http::get_http_settings,
+            http::set_http_settings,
+            endpoints::get_endpoint_settings,
+            endpoints::set_endpoint_settings,
             speaker::start_system_audio_capture,
             speaker::stop_system_audio_capture,
             speaker::check_system_audio_access,
@@ -159,6 +176,14 @@ pub fn run() {
             computer_use::computer_get_screen_size
         ])
         .setup(|app| {
+            // Shared HTTP client, configured from saved network settings
+            let http_settings = http::load_settings(app.handle());
+            app.manage(http::HttpClientState::new(http_settings));
+            let endpoint_settings = endpoints::load_settings(app.handle());
+            app.manage(endpoints::EndpointState::new(endpoint_settings));
+            let redaction_settings = capture::load_redaction_settings(app.handle());
+            app.manage(capture::RedactionState::new(redaction_settings));
+
             // Setup main window positioning
             window::setup_main_window(app).expect("Failed to setup main window");
             
@@ -0,0 +1,72 @@
+// Cached Extab model catalog
+// The last models response is kept in app data with its ETag, so opening the
+// settings page doesn't hit the backend every time and the list still loads
+// offline.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+
+use crate::api::Model;
+use crate::error::ApiError;
+
+// How long a cached list is served without asking the backend
+pub const TTL_SECS: i64 = 60 * 60;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CachedModels {
+    pub models: Vec<Model>,
+    pub etag: Option<String>,
+    // Unix seconds when the backend last confirmed the list
+    pub fetched_at: i64,
+}
+
+impl CachedModels {
+    pub fn new(models: Vec<Model>, etag: Option<String>) -> Self {
+        CachedModels { models, etag, fetched_at: now() }
+    }
+
+    pub fn is_fresh(&self) -> bool {
+        (0..TTL_SECS).contains(&(now() - self.fetched_at))
+    }
+
+    /// Marks the list as confirmed after a 304.
+    pub fn touch(&mut self) {
+        self.fetched_at = now();
+    }
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+fn get_cache_path(app: &AppHandle) -> Result<PathBuf, ApiError> {
+    let app_data_dir = app.path().app_data_dir()
+        .map_err(|e| ApiError::Storage(format!("Failed to get app data directory: {}", e)))?;
+
+    fs::create_dir_all(&app_data_dir)
+        .map_err(|e| ApiError::Storage(format!("Failed to create app data directory: {}", e)))?;
+
+    Ok(app_data_dir.join("models_cache.json"))
+}
+
+/// Reads the cached list, `None` when nothing usable is cached.
+pub fn load(app: &AppHandle) -> Option<CachedModels> {
+    get_cache_path(app)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+}
+
+/// Writes the list, a failed write only costs the next request its cache hit.
+pub fn save(app: &AppHandle, cached: &CachedModels) {
+    let result = get_cache_path(app).and_then(|path| {
+        let content = serde_json::to_string(cached)
+            .map_err(|e| ApiError::Storage(format!("Failed to serialize models cache: {}", e)))?;
+        fs::write(path, content).map_err(|e| ApiError::Storage(format!("Failed to write models cache: {}", e)))
+    });
+    if let Err(e) = result {
+        eprintln!("{}", e);
+    }
+}
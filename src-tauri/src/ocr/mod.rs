@@ -0,0 +1,123 @@
+// Local OCR of screenshots
+// Recognizers run entirely on this machine, nothing is sent over the network
+
+mod tesseract;
+
+use base64::Engine;
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::capture::{self, MonitorTarget};
+
+/// A recognized word. Boxes are in pixels of the image that was recognized.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OcrWord {
+    pub text: String,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    // 0-100, negative when the backend doesn't report one
+    pub confidence: f32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OcrLine {
+    pub text: String,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    // Indices into `OcrResult::words`
+    pub words: Vec<usize>,
+}
+
+impl OcrLine {
+    pub fn from_words(words: &[OcrWord], indices: Vec<usize>) -> Self {
+        let line_words: Vec<&OcrWord> = indices.iter().map(|i| &words[*i]).collect();
+
+        let left = line_words.iter().map(|w| w.x).min().unwrap_or(0);
+        let top = line_words.iter().map(|w| w.y).min().unwrap_or(0);
+        let right = line_words.iter().map(|w| w.x + w.width).max().unwrap_or(0);
+        let bottom = line_words.iter().map(|w| w.y + w.height).max().unwrap_or(0);
+
+        OcrLine {
+            text: line_words.iter().map(|w| w.text.as_str()).collect::<Vec<_>>().join(" "),
+            x: left,
+            y: top,
+            width: right - left,
+            height: bottom - top,
+            words: indices,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OcrResult {
+    pub text: String,
+    pub lines: Vec<OcrLine>,
+    pub words: Vec<OcrWord>,
+    // Size of the recognized image
+    pub width: u32,
+    pub height: u32,
+}
+
+// Backend-agnostic interface
+pub trait TextRecognizer {
+    fn recognize(&self, image: &RgbaImage) -> Result<OcrResult, String>;
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct OcrOptions {
+    // Backend language code, e.g. "eng" or "eng+deu" for tesseract
+    pub language: Option<String>,
+}
+
+// Get the configured recognizer
+pub fn get_text_recognizer(options: &OcrOptions) -> Box<dyn TextRecognizer + Send + Sync> {
+    Box::new(tesseract::TesseractRecognizer::new(options.language.clone()))
+}
+
+pub fn decode_base64_image(image_base64: &str) -> Result<RgbaImage, String> {
+    // Accept data URLs as well as bare base64
+    let data = image_base64
+        .split_once(";base64,")
+        .map_or(image_base64, |(_, data)| data);
+
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(data.trim())
+        .map_err(|e| format!("Failed to decode base64 image: {}", e))?;
+
+    let image = image::load_from_memory(&bytes).map_err(|e| format!("Failed to decode image: {}", e))?;
+    Ok(image.to_rgba8())
+}
+
+// Tauri commands
+#[tauri::command]
+pub async fn ocr_image(image_base64: String, options: Option<OcrOptions>) -> Result<OcrResult, String> {
+    let options = options.unwrap_or_default();
+    tokio::task::spawn_blocking(move || {
+        let image = decode_base64_image(&image_base64)?;
+        get_text_recognizer(&options).recognize(&image)
+    })
+    .await
+    .map_err(|e| format!("OCR task failed: {}", e))?
+}
+
+#[tauri::command]
+pub async fn ocr_screen(
+    app: AppHandle,
+    target: Option<MonitorTarget>,
+    options: Option<OcrOptions>,
+) -> Result<OcrResult, String> {
+    let target = target.unwrap_or_default();
+    let options = options.unwrap_or_default();
+    tokio::task::spawn_blocking(move || {
+        let image = capture::capture_image(&app, &target)?;
+        get_text_recognizer(&options).recognize(&image)
+    })
+    .await
+    .map_err(|e| format!("OCR task failed: {}", e))?
+}
@@ -0,0 +1,162 @@
+// Tesseract backend: shells out to a locally installed `tesseract` binary
+
+use super::{OcrLine, OcrResult, OcrWord, TextRecognizer};
+use image::{ImageFormat, RgbaImage};
+use std::collections::BTreeMap;
+use std::env;
+use std::io::{Cursor, Write};
+use std::process::{Command, Stdio};
+
+// TSV row level for individual words
+const WORD_LEVEL: &str = "5";
+
+pub struct TesseractRecognizer {
+    binary: String,
+    language: String,
+}
+
+impl TesseractRecognizer {
+    // Uses TESSERACT_PATH when set, otherwise looks up `tesseract` on PATH
+    pub fn new(language: Option<String>) -> Self {
+        TesseractRecognizer {
+            binary: env::var("TESSERACT_PATH").unwrap_or_else(|_| "tesseract".to_string()),
+            language: language.unwrap_or_else(|| "eng".to_string()),
+        }
+    }
+
+    fn run(&self, png: &[u8]) -> Result<String, String> {
+        let mut child = Command::new(&self.binary)
+            .args(["stdin", "stdout", "-l", &self.language, "tsv"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to start tesseract ({}): {}", self.binary, e))?;
+
+        // Write stdin from its own thread so a full stdout pipe can't deadlock us
+        let mut stdin = child.stdin.take().ok_or("Failed to open tesseract stdin".to_string())?;
+        let input = png.to_vec();
+        let writer = std::thread::spawn(move || stdin.write_all(&input));
+
+        let output = child
+            .wait_with_output()
+            .map_err(|e| format!("Failed to run tesseract: {}", e))?;
+
+        writer
+            .join()
+            .map_err(|_| "Failed to write image to tesseract".to_string())?
+            .map_err(|e| format!("Failed to write image to tesseract: {}", e))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("Tesseract failed: {}", stderr.trim()));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+fn parse_tsv(tsv: &str, width: u32, height: u32) -> OcrResult {
+    let mut words = Vec::new();
+    // Keyed by (block, paragraph, line) so lines come out in reading order
+    let mut lines: BTreeMap<(u32, u32, u32), Vec<usize>> = BTreeMap::new();
+
+    for row in tsv.lines().skip(1) {
+        let columns: Vec<&str> = row.split('\t').collect();
+        if columns.len() < 12 || columns[0] != WORD_LEVEL {
+            continue;
+        }
+
+        // Tesseract reports -1 for rows it didn't recognize text in
+        let text = columns[11].trim();
+        let confidence = columns[10].parse::<f32>().unwrap_or(-1.0);
+        if text.is_empty() || confidence < 0.0 {
+            continue;
+        }
+
+        let number = |i: usize| columns[i].parse::<u32>().unwrap_or(0);
+        words.push(OcrWord {
+            text: text.to_string(),
+            x: number(6),
+            y: number(7),
+            width: number(8),
+            height: number(9),
+            confidence,
+        });
+
+        lines
+            .entry((number(2), number(3), number(4)))
+            .or_default()
+            .push(words.len() - 1);
+    }
+
+    let lines: Vec<OcrLine> = lines.into_values().map(|indices| OcrLine::from_words(&words, indices)).collect();
+    let text = lines.iter().map(|l| l.text.as_str()).collect::<Vec<_>>().join("\n");
+
+    OcrResult {
+        text,
+        lines,
+        words,
+        width,
+        height,
+    }
+}
+
+impl TextRecognizer for TesseractRecognizer {
+    fn recognize(&self, image: &RgbaImage) -> Result<OcrResult, String> {
+        let mut png = Cursor::new(Vec::new());
+        image
+            .write_to(&mut png, ImageFormat::Png)
+            .map_err(|e| format!("Failed to encode image for OCR: {}", e))?;
+
+        let tsv = self.run(png.get_ref())?;
+        Ok(parse_tsv(&tsv, image.width(), image.height()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // level page_num block_num par_num line_num word_num left top width height conf text
+    const TSV: &str = "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext
+1\t1\t0\t0\t0\t0\t0\t0\t400\t200\t-1\t
+2\t1\t1\t0\t0\t0\t10\t10\t300\t80\t-1\t
+4\t1\t1\t1\t1\t0\t10\t10\t200\t20\t-1\t
+5\t1\t1\t1\t1\t1\t10\t12\t60\t18\t96.5\tHello
+5\t1\t1\t1\t1\t2\t80\t10\t70\t20\t91\tworld
+5\t1\t1\t1\t1\t3\t160\t10\t5\t20\t-1\t 
+5\t1\t1\t1\t2\t1\t12\t50\t90\t22\t88\tSecond
+5\t1\t1\t1\t2\t2\t110\t52\t40\t18\t-1\tnoise
+5\t1\t2\t1\t1\t1\t20\t120\t50\t20\t79\tNext
+";
+
+    #[test]
+    fn groups_words_into_lines() {
+        let result = parse_tsv(TSV, 400, 200);
+
+        let words: Vec<&str> = result.words.iter().map(|w| w.text.as_str()).collect();
+        assert_eq!(words, vec!["Hello", "world", "Second", "Next"]);
+        assert_eq!(result.words[0].confidence, 96.5);
+        assert_eq!(result.text, "Hello world\nSecond\nNext");
+        assert_eq!(result.lines[0].words, vec![0, 1]);
+        assert_eq!((result.width, result.height), (400, 200));
+    }
+
+    #[test]
+    fn line_boxes_cover_their_words() {
+        let result = parse_tsv(TSV, 400, 200);
+        let line = &result.lines[0];
+        assert_eq!((line.x, line.y, line.width, line.height), (10, 10, 140, 20));
+        let line = &result.lines[1];
+        assert_eq!((line.x, line.y, line.width, line.height), (12, 50, 90, 22));
+    }
+
+    #[test]
+    fn skips_short_and_non_word_rows() {
+        let result = parse_tsv("header\n5\t1\t1\t1\t1\t1\t0\t0\t10\t10\t90\n4\t1\t1\t1\t1\t0\t0\t0\t10\t10\t-1\tline\n", 10, 10);
+        assert!(result.words.is_empty());
+        assert!(result.lines.is_empty());
+        assert_eq!(result.text, "");
+    }
+}
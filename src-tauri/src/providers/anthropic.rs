@@ -0,0 +1,187 @@
+// Anthropic Messages API streaming
+
+use serde_json::{json, Value};
+
+use super::{apply_headers, ChatDelta, ChatMessage, ChatProvider, ContentPart, ProviderConfig, ProviderRequest, ReasoningMode, Role};
+use crate::error::ApiError;
+use crate::sse::SseEvent;
+use crate::usage::TokenUsage;
+
+const DEFAULT_BASE_URL: &str = "https://api.anthropic.com/v1";
+const API_VERSION: &str = "2023-06-01";
+// The Messages API requires an explicit output limit
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+const DEFAULT_THINKING_BUDGET: u32 = 2048;
+// Smallest budget the API accepts
+const MIN_THINKING_BUDGET: u32 = 1024;
+
+pub struct AnthropicProvider {
+    config: ProviderConfig,
+}
+
+impl AnthropicProvider {
+    pub fn new(config: ProviderConfig) -> Self {
+        AnthropicProvider { config }
+    }
+}
+
+fn message_json(message: &ChatMessage) -> Value {
+    // Tool results go back in a user turn
+    let role = match message.role {
+        Role::Assistant => "assistant",
+        _ => "user",
+    };
+
+    let content: Vec<Value> = message
+        .content
+        .iter()
+        .map(|part| match part {
+            ContentPart::Text { text } => json!({ "type": "text", "text": text }),
+            ContentPart::Image { .. } => {
+                let (mime, data) = part.image_data().unwrap_or_default();
+                json!({ "type": "image", "source": { "type": "base64", "media_type": mime, "data": data } })
+            }
+            ContentPart::ToolCall(call) => json!({
+                "type": "tool_use",
+                "id": call.id,
+                "name": call.name,
+                "input": call.arguments,
+            }),
+            ContentPart::ToolResult(result) => json!({
+                "type": "tool_result",
+                "tool_use_id": result.tool_call_id,
+                "content": result.content,
+                "is_error": result.is_error,
+            }),
+        })
+        .collect();
+    json!({ "role": role, "content": content })
+}
+
+impl ChatProvider for AnthropicProvider {
+    fn build_request(&self, client: &reqwest::Client, request: &ProviderRequest) -> reqwest::RequestBuilder {
+        let generation = &request.generation;
+        let max_tokens = generation.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS);
+        let mut body = json!({
+            "model": self.config.model,
+            "messages": request.conversation().map(message_json).collect::<Vec<_>>(),
+            "max_tokens": max_tokens,
+            "stream": true,
+        });
+        if let Some(system) = request.system_prompt() {
+            body["system"] = json!(system);
+        }
+        // There's no native JSON mode, so `response_format` is left to the prompt
+        if let Some(stop) = &generation.stop {
+            body["stop_sequences"] = json!(stop);
+        }
+        // Thinking blocks aren't replayed, so `submit_tool_results` turns
+        // thinking off for the rest of a tool round
+        if request.reasoning.mode == ReasoningMode::Enabled {
+            let budget = request
+                .reasoning
+                .budget_tokens
+                .unwrap_or(DEFAULT_THINKING_BUDGET)
+                .max(MIN_THINKING_BUDGET);
+            body["thinking"] = json!({ "type": "enabled", "budget_tokens": budget });
+            // The budget counts against max_tokens
+            body["max_tokens"] = json!(max_tokens + budget);
+        } else {
+            // Thinking requires the default sampling. Newer models reject
+            // temperature and top_p together, so temperature wins
+            if let Some(temperature) = generation.temperature {
+                body["temperature"] = json!(temperature);
+            } else if let Some(top_p) = generation.top_p {
+                body["top_p"] = json!(top_p);
+            }
+        }
+        if !request.tools.is_empty() {
+            let tools: Vec<Value> = request
+                .tools
+                .iter()
+                .map(|tool| {
+                    json!({
+                        "name": tool.name,
+                        "description": tool.description.clone().unwrap_or_default(),
+                        "input_schema": tool.parameters,
+                    })
+                })
+                .collect();
+            body["tools"] = json!(tools);
+        }
+
+        let url = format!("{}/messages", self.config.base_url(DEFAULT_BASE_URL));
+        let builder = client
+            .post(url)
+            .header("anthropic-version", API_VERSION)
+            .header("x-api-key", self.config.api_key.as_deref().unwrap_or_default())
+            .json(&body);
+        apply_headers(builder, &self.config.headers)
+    }
+
+    fn parse_event(&mut self, event: &SseEvent) -> Result<Vec<ChatDelta>, ApiError> {
+        let json = event
+            .json()
+            .ok_or_else(|| ApiError::Parse(format!("Invalid chat stream event: {}", event.data)))?;
+        let index = json["index"].as_u64().unwrap_or_default() as usize;
+        let count = |value: &Value| value.as_u64().unwrap_or_default();
+
+        let delta = match json["type"].as_str().unwrap_or_default() {
+            "message_start" => {
+                let usage = &json["message"]["usage"];
+                let cached = count(&usage["cache_read_input_tokens"]);
+                let written = count(&usage["cache_creation_input_tokens"]);
+                Some(ChatDelta::Usage {
+                    usage: TokenUsage {
+                        // Anthropic reports cache reads and writes apart from input_tokens
+                        input_tokens: count(&usage["input_tokens"]) + cached + written,
+                        output_tokens: count(&usage["output_tokens"]),
+                        reasoning_tokens: 0,
+                        cached_input_tokens: cached,
+                        cache_write_input_tokens: written,
+                    },
+                })
+            }
+            "content_block_start" if json["content_block"]["type"] == "tool_use" => Some(ChatDelta::ToolCall {
+                index,
+                id: json["content_block"]["id"].as_str().map(|s| s.to_string()),
+                name: json["content_block"]["name"].as_str().map(|s| s.to_string()),
+                arguments: String::new(),
+            }),
+            "content_block_delta" => {
+                let delta = &json["delta"];
+                match delta["type"].as_str().unwrap_or_default() {
+                    "text_delta" => Some(ChatDelta::Text {
+                        text: delta["text"].as_str().unwrap_or_default().to_string(),
+                    }),
+                    "thinking_delta" => Some(ChatDelta::Reasoning {
+                        text: delta["thinking"].as_str().unwrap_or_default().to_string(),
+                    }),
+                    "input_json_delta" => Some(ChatDelta::ToolCall {
+                        index,
+                        id: None,
+                        name: None,
+                        arguments: delta["partial_json"].as_str().unwrap_or_default().to_string(),
+                    }),
+                    _ => None,
+                }
+            }
+            "message_delta" => {
+                let mut deltas = vec![ChatDelta::Usage {
+                    usage: TokenUsage {
+                        output_tokens: count(&json["usage"]["output_tokens"]),
+                        ..TokenUsage::default()
+                    },
+                }];
+                if let Some(reason) = json["delta"]["stop_reason"].as_str() {
+                    deltas.push(ChatDelta::Done { finish_reason: Some(reason.to_string()) });
+                }
+                return Ok(deltas);
+            }
+            // content_block_stop, message_stop and ping carry nothing to forward
+            _ => None,
+        };
+
+        Ok(delta.into_iter().collect())
+    }
+}
@@ -0,0 +1,175 @@
+// Google Gemini streamGenerateContent over SSE
+
+use serde_json::{json, Value};
+
+use super::{
+    apply_headers, ChatDelta, ChatMessage, ChatProvider, ContentPart, ProviderConfig, ProviderRequest,
+    ReasoningMode, ResponseFormat, Role,
+};
+use crate::error::ApiError;
+use crate::sse::SseEvent;
+use crate::usage::TokenUsage;
+
+const DEFAULT_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+
+pub struct GeminiProvider {
+    config: ProviderConfig,
+    // Gemini sends whole function calls without indices, so number them here
+    tool_calls: usize,
+}
+
+impl GeminiProvider {
+    pub fn new(config: ProviderConfig) -> Self {
+        GeminiProvider { config, tool_calls: 0 }
+    }
+}
+
+fn message_json(message: &ChatMessage) -> Value {
+    let role = match message.role {
+        Role::Assistant => "model",
+        _ => "user",
+    };
+
+    let parts: Vec<Value> = message
+        .content
+        .iter()
+        .map(|part| match part {
+            ContentPart::Text { text } => json!({ "text": text }),
+            ContentPart::Image { .. } => {
+                let (mime, data) = part.image_data().unwrap_or_default();
+                json!({ "inline_data": { "mime_type": mime, "data": data } })
+            }
+            ContentPart::ToolCall(call) => json!({ "functionCall": { "name": call.name, "args": call.arguments } }),
+            ContentPart::ToolResult(result) => json!({
+                "functionResponse": { "name": result.name, "response": { "content": result.content } },
+            }),
+        })
+        .collect();
+    json!({ "role": role, "parts": parts })
+}
+
+impl ChatProvider for GeminiProvider {
+    fn build_request(&self, client: &reqwest::Client, request: &ProviderRequest) -> reqwest::RequestBuilder {
+        let mut body = json!({
+            "contents": request.conversation().map(message_json).collect::<Vec<_>>(),
+        });
+        if let Some(system) = request.system_prompt() {
+            body["systemInstruction"] = json!({ "parts": [{ "text": system }] });
+        }
+        if !request.tools.is_empty() {
+            let declarations: Vec<Value> = request
+                .tools
+                .iter()
+                .map(|tool| {
+                    json!({
+                        "name": tool.name,
+                        "description": tool.description.clone().unwrap_or_default(),
+                        "parameters": tool.parameters,
+                    })
+                })
+                .collect();
+            body["tools"] = json!([{ "functionDeclarations": declarations }]);
+        }
+
+        let reasoning = &request.reasoning;
+        let thinking = match reasoning.mode {
+            ReasoningMode::Enabled => {
+                let mut config = json!({ "includeThoughts": !reasoning.hide });
+                if let Some(budget) = reasoning.budget_tokens {
+                    config["thinkingBudget"] = json!(budget);
+                }
+                Some(config)
+            }
+            ReasoningMode::Disabled => Some(json!({ "thinkingBudget": 0 })),
+            ReasoningMode::Default => reasoning.budget_tokens.map(|budget| json!({ "thinkingBudget": budget })),
+        };
+        let generation = &request.generation;
+        let mut config = json!({});
+        if let Some(thinking) = thinking {
+            config["thinkingConfig"] = thinking;
+        }
+        if let Some(temperature) = generation.temperature {
+            config["temperature"] = json!(temperature);
+        }
+        if let Some(max_tokens) = generation.max_tokens {
+            config["maxOutputTokens"] = json!(max_tokens);
+        }
+        if let Some(top_p) = generation.top_p {
+            config["topP"] = json!(top_p);
+        }
+        if let Some(stop) = &generation.stop {
+            config["stopSequences"] = json!(stop);
+        }
+        match &generation.response_format {
+            Some(ResponseFormat::JsonObject) => config["responseMimeType"] = json!("application/json"),
+            Some(ResponseFormat::JsonSchema { json_schema }) => {
+                config["responseMimeType"] = json!("application/json");
+                config["responseJsonSchema"] = json_schema.schema.clone();
+            }
+            Some(ResponseFormat::Text) | None => {}
+        }
+        if config.as_object().is_some_and(|c| !c.is_empty()) {
+            body["generationConfig"] = config;
+        }
+
+        let url = format!(
+            "{}/models/{}:streamGenerateContent?alt=sse",
+            self.config.base_url(DEFAULT_BASE_URL),
+            self.config.model
+        );
+        let builder = client
+            .post(url)
+            .header("x-goog-api-key", self.config.api_key.as_deref().unwrap_or_default())
+            .json(&body);
+        apply_headers(builder, &self.config.headers)
+    }
+
+    fn parse_event(&mut self, event: &SseEvent) -> Result<Vec<ChatDelta>, ApiError> {
+        let json = event
+            .json()
+            .ok_or_else(|| ApiError::Parse(format!("Invalid chat stream event: {}", event.data)))?;
+        let candidate = &json["candidates"][0];
+        let mut deltas = Vec::new();
+
+        for part in candidate["content"]["parts"].as_array().into_iter().flatten() {
+            if let Some(call) = part.get("functionCall") {
+                deltas.push(ChatDelta::ToolCall {
+                    index: self.tool_calls,
+                    id: call["id"].as_str().map(|s| s.to_string()),
+                    name: call["name"].as_str().map(|s| s.to_string()),
+                    arguments: call.get("args").map_or_else(|| "{}".to_string(), |args| args.to_string()),
+                });
+                self.tool_calls += 1;
+            } else if let Some(text) = part["text"].as_str().filter(|t| !t.is_empty()) {
+                // Thought summaries are flagged with `thought: true`
+                if part["thought"].as_bool().unwrap_or(false) {
+                    deltas.push(ChatDelta::Reasoning { text: text.to_string() });
+                } else {
+                    deltas.push(ChatDelta::Text { text: text.to_string() });
+                }
+            }
+        }
+
+        // Running totals, repeated on every chunk
+        if let Some(metadata) = json["usageMetadata"].as_object() {
+            let count = |key: &str| metadata.get(key).and_then(|v| v.as_u64()).unwrap_or_default();
+            let thoughts = count("thoughtsTokenCount");
+            deltas.push(ChatDelta::Usage {
+                usage: TokenUsage {
+                    input_tokens: count("promptTokenCount"),
+                    // Thoughts are billed as output but reported apart
+                    output_tokens: count("candidatesTokenCount") + thoughts,
+                    reasoning_tokens: thoughts,
+                    cached_input_tokens: count("cachedContentTokenCount"),
+                    cache_write_input_tokens: 0,
+                },
+            });
+        }
+
+        if let Some(reason) = candidate["finishReason"].as_str() {
+            deltas.push(ChatDelta::Done { finish_reason: Some(reason.to_string()) });
+        }
+
+        Ok(deltas)
+    }
+}
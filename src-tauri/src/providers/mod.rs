@@ -0,0 +1,519 @@
+// Direct chat provider adapters for bring-your-own-key users
+// Each adapter speaks one streaming protocol and turns it into the same
+// normalized deltas, so the rest of the app never sees provider formats.
+
+mod anthropic;
+mod gemini;
+pub mod openai;
+mod tools;
+
+pub use tools::*;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+use crate::api;
+use crate::context;
+use crate::error::ApiError;
+use crate::http;
+use crate::retry;
+use crate::sse::{SseEvent, SseStream};
+use crate::usage::{self, TokenUsage};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ProviderKind {
+    // OpenAI and compatible servers: Ollama, OpenRouter, Groq, LM Studio...
+    #[serde(rename = "openai")]
+    OpenAi,
+    #[serde(rename = "anthropic")]
+    Anthropic,
+    #[serde(rename = "gemini")]
+    Gemini,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ProviderConfig {
+    pub kind: ProviderKind,
+    // Defaults to the provider's public API
+    #[serde(default)]
+    pub base_url: Option<String>,
+    #[serde(default)]
+    pub api_key: Option<String>,
+    pub model: String,
+    // Extra headers sent with every request
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+impl ProviderKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            ProviderKind::OpenAi => "openai",
+            ProviderKind::Anthropic => "anthropic",
+            ProviderKind::Gemini => "gemini",
+        }
+    }
+}
+
+impl ProviderConfig {
+    fn base_url(&self, default: &str) -> String {
+        self.base_url
+            .as_deref()
+            .filter(|url| !url.trim().is_empty())
+            .unwrap_or(default)
+            .trim()
+            .trim_end_matches('/')
+            .to_string()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+    // Tool results sent back to the model
+    Tool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentPart {
+    Text { text: String },
+    // Base64 image data or a data URL
+    Image { data: String, mime_type: Option<String> },
+    // Calls requested by the assistant
+    ToolCall(ToolCall),
+    ToolResult(ToolResult),
+}
+
+impl ContentPart {
+    /// Splits an image part into (mime type, raw base64).
+    pub(crate) fn image_data(&self) -> Option<(String, &str)> {
+        let ContentPart::Image { data, mime_type } = self else {
+            return None;
+        };
+
+        if let Some((header, base64)) = data.strip_prefix("data:").and_then(|d| d.split_once(',')) {
+            let mime = header.trim_end_matches(";base64");
+            return Some((mime.to_string(), base64));
+        }
+        Some((mime_type.clone().unwrap_or_else(|| "image/png".to_string()), data))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ChatMessage {
+    pub role: Role,
+    pub content: Vec<ContentPart>,
+}
+
+impl ChatMessage {
+    /// All text parts joined, images and tool parts skipped.
+    pub fn text_content(&self) -> String {
+        self.content
+            .iter()
+            .filter_map(|part| match part {
+                ContentPart::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ReasoningMode {
+    // Whatever the model does by default
+    #[default]
+    Default,
+    Enabled,
+    // Only honoured where the provider can turn thinking off
+    Disabled,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ReasoningOptions {
+    pub mode: ReasoningMode,
+    // Thinking token budget for Anthropic and Gemini
+    pub budget_tokens: Option<u32>,
+    // "low", "medium" or "high", for OpenAI-style reasoning models
+    pub effort: Option<String>,
+    // Don't surface reasoning at all, in events or in the output
+    pub hide: bool,
+}
+
+// Output format in the OpenAI `response_format` shape, mapped per provider
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    Text,
+    JsonObject,
+    JsonSchema { json_schema: JsonSchemaFormat },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct JsonSchemaFormat {
+    pub name: String,
+    pub schema: serde_json::Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+}
+
+/// Per-request model and sampling overrides. Unset fields keep the
+/// provider's defaults.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct GenerationOptions {
+    // Sent as the model header for Extab, never in the body
+    #[serde(skip_serializing)]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+}
+
+impl GenerationOptions {
+    pub fn validate(&self) -> Result<(), ApiError> {
+        let invalid = |message: &str| Err(ApiError::Config(message.to_string()));
+
+        if self.model.as_deref().is_some_and(|m| m.trim().is_empty()) {
+            return invalid("Model override can't be empty");
+        }
+        if self.temperature.is_some_and(|t| !(0.0..=2.0).contains(&t)) {
+            return invalid("Temperature must be between 0 and 2");
+        }
+        if self.top_p.is_some_and(|p| !(0.0..=1.0).contains(&p)) {
+            return invalid("top_p must be between 0 and 1");
+        }
+        if self.max_tokens == Some(0) {
+            return invalid("max_tokens must be greater than 0");
+        }
+        if self.stop.as_ref().is_some_and(|stop| stop.iter().any(|s| s.is_empty())) {
+            return invalid("Stop sequences can't be empty");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ProviderRequest {
+    pub messages: Vec<ChatMessage>,
+    pub tools: Vec<ToolDefinition>,
+    pub reasoning: ReasoningOptions,
+    pub generation: GenerationOptions,
+}
+
+impl ProviderRequest {
+    /// System messages joined, for protocols that take the system prompt separately.
+    fn system_prompt(&self) -> Option<String> {
+        let system: Vec<String> = self
+            .messages
+            .iter()
+            .filter(|m| m.role == Role::System)
+            .map(|m| m.text_content())
+            .collect();
+        (!system.is_empty()).then(|| system.join("\n\n"))
+    }
+
+    fn conversation(&self) -> impl Iterator<Item = &ChatMessage> {
+        self.messages.iter().filter(|m| m.role != Role::System)
+    }
+}
+
+/// Normalized streaming output shared by every provider.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChatDelta {
+    Text {
+        text: String,
+    },
+    Reasoning {
+        text: String,
+    },
+    // `id` and `name` arrive with the first delta of a call, `arguments`
+    // is a fragment of the JSON arguments to append
+    ToolCall {
+        index: usize,
+        id: Option<String>,
+        name: Option<String>,
+        arguments: String,
+    },
+    Usage {
+        usage: TokenUsage,
+    },
+    Done {
+        finish_reason: Option<String>,
+    },
+}
+
+// Protocol adapter. A new instance is created per request so adapters can
+// keep state across the events of one stream.
+pub trait ChatProvider: Send + Sync {
+    fn build_request(&self, client: &reqwest::Client, request: &ProviderRequest) -> reqwest::RequestBuilder;
+    fn parse_event(&mut self, event: &SseEvent) -> Result<Vec<ChatDelta>, ApiError>;
+}
+
+pub fn get_chat_provider(config: &ProviderConfig) -> Box<dyn ChatProvider> {
+    match config.kind {
+        ProviderKind::OpenAi => Box::new(openai::OpenAiProvider::new(config.clone())),
+        ProviderKind::Anthropic => Box::new(anthropic::AnthropicProvider::new(config.clone())),
+        ProviderKind::Gemini => Box::new(gemini::GeminiProvider::new(config.clone())),
+    }
+}
+
+fn apply_headers(builder: reqwest::RequestBuilder, headers: &HashMap<String, String>) -> reqwest::RequestBuilder {
+    headers.iter().fold(builder, |builder, (name, value)| builder.header(name, value))
+}
+
+/// Final result of a chat stream.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ChatOutput {
+    pub content: String,
+    // Accumulated reasoning, kept apart from the answer
+    pub reasoning: String,
+    // Set when the model is waiting on tool results, see `submit_tool_results`
+    pub tool_calls: Vec<ToolCall>,
+    pub finish_reason: Option<String>,
+    // None when the provider didn't report usage
+    pub usage: Option<TokenUsage>,
+    pub latency_ms: u64,
+    pub time_to_first_token_ms: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct ChatStreamChunk {
+    request_id: String,
+    content: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct ChatStreamToolCall {
+    request_id: String,
+    #[serde(flatten)]
+    call: ToolCallProgress,
+}
+
+/// Emits the stream events for one request and collects its output. Text goes
+/// out as `chat_stream_chunk`, reasoning as `chat_stream_reasoning`, tool call
+/// progress as `chat_stream_tool_call`. Usage and finish reason are only
+/// collected for the result.
+pub struct DeltaSink {
+    app: AppHandle,
+    request_id: String,
+    // Text so far, shared with the cancelled event
+    partial: Arc<Mutex<String>>,
+    reasoning: String,
+    hide_reasoning: bool,
+    tool_calls: ToolCallAssembler,
+    finish_reason: Option<String>,
+    usage: Option<TokenUsage>,
+    started: Instant,
+    first_token: Option<Duration>,
+}
+
+impl DeltaSink {
+    pub fn new(app: AppHandle, request_id: String, partial: Arc<Mutex<String>>) -> Self {
+        DeltaSink {
+            app,
+            request_id,
+            partial,
+            reasoning: String::new(),
+            hide_reasoning: false,
+            tool_calls: ToolCallAssembler::default(),
+            finish_reason: None,
+            usage: None,
+            started: Instant::now(),
+            first_token: None,
+        }
+    }
+
+    pub fn hide_reasoning(mut self, hide: bool) -> Self {
+        self.hide_reasoning = hide;
+        self
+    }
+
+    pub fn push(&mut self, delta: ChatDelta) {
+        if matches!(delta, ChatDelta::Text { .. } | ChatDelta::Reasoning { .. } | ChatDelta::ToolCall { .. }) {
+            self.first_token.get_or_insert_with(|| self.started.elapsed());
+        }
+
+        match &delta {
+            ChatDelta::Reasoning { .. } if self.hide_reasoning => {}
+            ChatDelta::Reasoning { text } => {
+                self.reasoning.push_str(text);
+                let _ = self.app.emit("chat_stream_reasoning", ChatStreamChunk {
+                    request_id: self.request_id.clone(),
+                    content: text.clone(),
+                });
+            }
+            ChatDelta::Text { text } => {
+                self.partial.lock().unwrap().push_str(text);
+                let _ = self.app.emit("chat_stream_chunk", ChatStreamChunk {
+                    request_id: self.request_id.clone(),
+                    content: text.clone(),
+                });
+            }
+            ChatDelta::ToolCall { .. } => {
+                if let Some(call) = self.tool_calls.push(&delta) {
+                    let _ = self.app.emit("chat_stream_tool_call", ChatStreamToolCall {
+                        request_id: self.request_id.clone(),
+                        call,
+                    });
+                }
+            }
+            ChatDelta::Usage { usage } => self.usage.get_or_insert_with(TokenUsage::default).merge(usage),
+            ChatDelta::Done { finish_reason } => self.finish_reason = finish_reason.clone(),
+        }
+    }
+
+    pub fn finish(self) -> Result<ChatOutput, ApiError> {
+        Ok(ChatOutput {
+            content: self.partial.lock().unwrap().clone(),
+            reasoning: self.reasoning,
+            tool_calls: self.tool_calls.finish()?,
+            finish_reason: self.finish_reason,
+            usage: self.usage,
+            latency_ms: self.started.elapsed().as_millis() as u64,
+            time_to_first_token_ms: self.first_token.map(|d| d.as_millis() as u64),
+        })
+    }
+}
+
+/// Sends the request and feeds every normalized delta to `sink` until the
+/// stream ends.
+pub async fn stream_chat(
+    app: &AppHandle,
+    config: &ProviderConfig,
+    request: &ProviderRequest,
+    sink: &mut DeltaSink,
+) -> Result<(), ApiError> {
+    let mut provider = get_chat_provider(config);
+    let client = http::client(app);
+
+    let response = retry::send_with_retry(app, "chat", || provider.build_request(&client, request)).await?;
+
+    let mut stream = SseStream::new(response.bytes_stream());
+    while let Some(event) = stream.next_event().await? {
+        for delta in provider.parse_event(&event)? {
+            sink.push(delta);
+        }
+    }
+
+    Ok(())
+}
+
+/// Streams `request` as a cancellable task under `request_id`.
+pub(crate) async fn run_provider_stream(
+    app: AppHandle,
+    request_id: String,
+    config: ProviderConfig,
+    request: ProviderRequest,
+) -> Result<ChatOutput, ApiError> {
+    let partial = Arc::new(Mutex::new(String::new()));
+    let mut sink = DeltaSink::new(app.clone(), request_id.clone(), partial.clone())
+        .hide_reasoning(request.reasoning.hide);
+
+    let task = {
+        let app = app.clone();
+        let request_id = request_id.clone();
+        async move {
+            stream_chat(&app, &config, &request, &mut sink).await?;
+            let output = sink.finish()?;
+            usage::record_chat(&app, config.kind.name(), &config.model, &output);
+            park_tool_round(&app, &request_id, ToolRoundOrigin::Provider { config, request }, &output);
+            Ok(output)
+        }
+    };
+
+    api::run_cancellable(&app, request_id, partial, task).await
+}
+
+/// Applies a model override to `config` and fits the messages into the
+/// model's context window.
+pub(crate) fn build_request(
+    config: &mut ProviderConfig,
+    messages: Vec<ChatMessage>,
+    tools: Vec<ToolDefinition>,
+    reasoning: ReasoningOptions,
+    mut generation: GenerationOptions,
+) -> Result<ProviderRequest, ApiError> {
+    generation.validate()?;
+    // Anthropic takes a narrower range than OpenAI and Gemini
+    if config.kind == ProviderKind::Anthropic && generation.temperature.is_some_and(|t| t > 1.0) {
+        return Err(ApiError::Config("Temperature must be between 0 and 1 for Anthropic".to_string()));
+    }
+    if let Some(model) = generation.model.take() {
+        config.model = model;
+    }
+
+    Ok(ProviderRequest {
+        messages: context::fit_messages(messages, Some(&config.model), generation.max_tokens)?,
+        tools,
+        reasoning,
+        generation,
+    })
+}
+
+/// Streams a `chat_stream`-style turn from a provider under `request_id`.
+pub(crate) async fn run_chat_input(
+    app: AppHandle,
+    request_id: String,
+    mut config: ProviderConfig,
+    input: api::ChatInput,
+) -> Result<ChatOutput, ApiError> {
+    let system = input.system_prompt.filter(|p| !p.trim().is_empty()).map(|text| ChatMessage {
+        role: Role::System,
+        content: vec![ContentPart::Text { text }],
+    });
+    let user = api::user_turn(&input.user_message, input.image_base64.as_ref());
+    let messages = system.into_iter().chain(input.history).chain([user]).collect();
+
+    let request = build_request(
+        &mut config,
+        messages,
+        input.tools,
+        input.reasoning.unwrap_or_default(),
+        input.generation,
+    )?;
+    run_provider_stream(app, request_id, config, request).await
+}
+
+/// Streams a chat directly from a provider, with the same events as
+/// `chat_stream`. `options` can swap the model and sampling parameters for
+/// this call. Older turns are trimmed to the model's context window.
+/// Cancel with `cancel_chat_stream`.
+#[tauri::command]
+pub async fn provider_chat_stream(
+    app: AppHandle,
+    mut provider: ProviderConfig,
+    messages: Vec<ChatMessage>,
+    tools: Option<Vec<ToolDefinition>>,
+    reasoning: Option<ReasoningOptions>,
+    options: Option<GenerationOptions>,
+    request_id: Option<String>,
+) -> Result<ChatOutput, ApiError> {
+    let request_id = request_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let request = build_request(
+        &mut provider,
+        messages,
+        tools.unwrap_or_default(),
+        reasoning.unwrap_or_default(),
+        options.unwrap_or_default(),
+    )?;
+
+    run_provider_stream(app, request_id, provider, request).await
+}
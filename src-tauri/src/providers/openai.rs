@@ -0,0 +1,193 @@
+// OpenAI Chat Completions streaming, also spoken by most compatible servers
+
+use serde_json::{json, Value};
+
+use super::{
+    apply_headers, ChatDelta, ChatMessage, ChatProvider, ContentPart, ProviderConfig, ProviderRequest,
+    ReasoningMode, Role, ToolDefinition,
+};
+use crate::error::ApiError;
+use crate::sse::SseEvent;
+use crate::usage::TokenUsage;
+
+const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+
+pub struct OpenAiProvider {
+    config: ProviderConfig,
+}
+
+impl OpenAiProvider {
+    pub fn new(config: ProviderConfig) -> Self {
+        OpenAiProvider { config }
+    }
+}
+
+/// Converts a message, tool messages expand to one message per result.
+pub fn message_json(message: &ChatMessage) -> Vec<Value> {
+    let role = match message.role {
+        Role::System => "system",
+        Role::User => "user",
+        Role::Assistant => "assistant",
+        Role::Tool => {
+            return message
+                .content
+                .iter()
+                .filter_map(|part| match part {
+                    ContentPart::ToolResult(result) => Some(json!({
+                        "role": "tool",
+                        "tool_call_id": result.tool_call_id,
+                        "content": result.content,
+                    })),
+                    _ => None,
+                })
+                .collect();
+        }
+    };
+
+    let tool_calls: Vec<Value> = message
+        .content
+        .iter()
+        .filter_map(|part| match part {
+            ContentPart::ToolCall(call) => Some(json!({
+                "id": call.id,
+                "type": "function",
+                "function": { "name": call.name, "arguments": call.arguments.to_string() },
+            })),
+            _ => None,
+        })
+        .collect();
+    if !tool_calls.is_empty() {
+        let text = message.text_content();
+        let content = if text.is_empty() { Value::Null } else { json!(text) };
+        return vec![json!({ "role": role, "content": content, "tool_calls": tool_calls })];
+    }
+
+    // Plain string content is the most widely supported form
+    if message.content.iter().all(|part| matches!(part, ContentPart::Text { .. })) {
+        return vec![json!({ "role": role, "content": message.text_content() })];
+    }
+
+    let content: Vec<Value> = message
+        .content
+        .iter()
+        .filter_map(|part| match part {
+            ContentPart::Text { text } => Some(json!({ "type": "text", "text": text })),
+            ContentPart::Image { .. } => {
+                let (mime, data) = part.image_data().unwrap_or_default();
+                Some(json!({ "type": "image_url", "image_url": { "url": format!("data:{};base64,{}", mime, data) } }))
+            }
+            _ => None,
+        })
+        .collect();
+    vec![json!({ "role": role, "content": content })]
+}
+
+pub fn tools_json(tools: &[ToolDefinition]) -> Vec<Value> {
+    tools
+        .iter()
+        .map(|tool| {
+            json!({
+                "type": "function",
+                "function": {
+                    "name": tool.name,
+                    "description": tool.description.clone().unwrap_or_default(),
+                    "parameters": tool.parameters,
+                },
+            })
+        })
+        .collect()
+}
+
+/// Normalizes one streamed chunk. Shared with the Extab endpoint, which
+/// streams the same format.
+pub fn parse_chunk(chunk: &Value) -> Vec<ChatDelta> {
+    let mut deltas = Vec::new();
+    let choice = &chunk["choices"][0];
+    let delta = &choice["delta"];
+
+    // `reasoning_content` from DeepSeek-style servers, `reasoning` from OpenRouter
+    for key in ["reasoning_content", "reasoning"] {
+        if let Some(text) = delta[key].as_str().filter(|t| !t.is_empty()) {
+            deltas.push(ChatDelta::Reasoning { text: text.to_string() });
+        }
+    }
+
+    if let Some(text) = delta["content"].as_str().filter(|t| !t.is_empty()) {
+        deltas.push(ChatDelta::Text { text: text.to_string() });
+    }
+
+    for (position, call) in delta["tool_calls"].as_array().into_iter().flatten().enumerate() {
+        deltas.push(ChatDelta::ToolCall {
+            index: call["index"].as_u64().map_or(position, |i| i as usize),
+            id: call["id"].as_str().map(|s| s.to_string()),
+            name: call["function"]["name"].as_str().map(|s| s.to_string()),
+            arguments: call["function"]["arguments"].as_str().unwrap_or_default().to_string(),
+        });
+    }
+
+    // Sent on a final chunk with no choices when usage is requested
+    if let Some(usage) = chunk["usage"].as_object() {
+        let count = |value: &Value| value.as_u64().unwrap_or_default();
+        deltas.push(ChatDelta::Usage {
+            usage: TokenUsage {
+                input_tokens: count(&usage["prompt_tokens"]),
+                output_tokens: count(&usage["completion_tokens"]),
+                reasoning_tokens: count(&usage["completion_tokens_details"]["reasoning_tokens"]),
+                cached_input_tokens: count(&usage["prompt_tokens_details"]["cached_tokens"]),
+                cache_write_input_tokens: 0,
+            },
+        });
+    }
+
+    if let Some(reason) = choice["finish_reason"].as_str() {
+        deltas.push(ChatDelta::Done { finish_reason: Some(reason.to_string()) });
+    }
+
+    deltas
+}
+
+impl ChatProvider for OpenAiProvider {
+    fn build_request(&self, client: &reqwest::Client, request: &ProviderRequest) -> reqwest::RequestBuilder {
+        let mut body = json!({
+            "model": self.config.model,
+            "messages": request.messages.iter().flat_map(message_json).collect::<Vec<_>>(),
+            "stream": true,
+            "stream_options": { "include_usage": true },
+        });
+        if !request.tools.is_empty() {
+            body["tools"] = json!(tools_json(&request.tools));
+        }
+        // Already in Chat Completions field names
+        if let Value::Object(options) = json!(request.generation) {
+            body.as_object_mut().unwrap().extend(options);
+        }
+        // OpenAI's reasoning models only accept the newer name, while many
+        // compatible servers only know `max_tokens`
+        let base_url = self.config.base_url(DEFAULT_BASE_URL);
+        if base_url == DEFAULT_BASE_URL {
+            if let Some(max_tokens) = body.as_object_mut().unwrap().remove("max_tokens") {
+                body["max_completion_tokens"] = max_tokens;
+            }
+        }
+        // Chat Completions has no thinking budget, only an effort level
+        if let Some(effort) = request.reasoning.effort.as_deref() {
+            if request.reasoning.mode != ReasoningMode::Disabled {
+                body["reasoning_effort"] = json!(effort);
+            }
+        }
+
+        let url = format!("{}/chat/completions", base_url);
+        let mut builder = client.post(url).json(&body);
+        if let Some(key) = self.config.api_key.as_deref().filter(|k| !k.is_empty()) {
+            builder = builder.bearer_auth(key);
+        }
+        apply_headers(builder, &self.config.headers)
+    }
+
+    fn parse_event(&mut self, event: &SseEvent) -> Result<Vec<ChatDelta>, ApiError> {
+        let chunk = event
+            .json()
+            .ok_or_else(|| ApiError::Parse(format!("Invalid chat stream event: {}", event.data)))?;
+        Ok(parse_chunk(&chunk))
+    }
+}
@@ -0,0 +1,299 @@
+// Tool calling: streamed call assembly and continuing a chat with results
+//
+// When a stream ends with tool calls the conversation so far is parked under
+// its request id. `submit_tool_results` appends the results and streams the
+// model's follow-up under the same id. Rounds nobody submits results for are
+// dropped when the stream is cancelled or after `TOOL_ROUND_TTL`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+use super::{ChatDelta, ChatMessage, ChatOutput, ContentPart, ProviderConfig, ProviderKind, ProviderRequest, ReasoningMode, Role};
+use crate::api;
+use crate::error::ApiError;
+
+const TOOL_ROUND_TTL: Duration = Duration::from_secs(30 * 60);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ToolDefinition {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    // JSON Schema of the arguments object
+    pub parameters: serde_json::Value,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    pub arguments: serde_json::Value,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ToolResult {
+    pub tool_call_id: String,
+    // Gemini matches results by function name rather than id
+    pub name: String,
+    pub content: String,
+    #[serde(default)]
+    pub is_error: bool,
+}
+
+/// Progress of one tool call, emitted as `chat_stream_tool_call`.
+#[derive(Debug, Serialize, Clone)]
+pub struct ToolCallProgress {
+    pub index: usize,
+    pub id: Option<String>,
+    pub name: Option<String>,
+    // Fragment received in this delta
+    pub arguments_delta: String,
+    // All argument text received so far, not valid JSON until the call ends
+    pub arguments: String,
+}
+
+#[derive(Debug, Default)]
+struct PartialToolCall {
+    id: Option<String>,
+    name: Option<String>,
+    arguments: String,
+}
+
+/// Joins streamed tool call fragments by index.
+#[derive(Debug, Default)]
+pub struct ToolCallAssembler {
+    calls: BTreeMap<usize, PartialToolCall>,
+}
+
+impl ToolCallAssembler {
+    pub fn push(&mut self, delta: &ChatDelta) -> Option<ToolCallProgress> {
+        let ChatDelta::ToolCall { index, id, name, arguments } = delta else {
+            return None;
+        };
+
+        let call = self.calls.entry(*index).or_default();
+        if id.is_some() {
+            call.id = id.clone();
+        }
+        if name.is_some() {
+            call.name = name.clone();
+        }
+        call.arguments.push_str(arguments);
+
+        Some(ToolCallProgress {
+            index: *index,
+            id: call.id.clone(),
+            name: call.name.clone(),
+            arguments_delta: arguments.clone(),
+            arguments: call.arguments.clone(),
+        })
+    }
+
+    pub fn finish(self) -> Result<Vec<ToolCall>, ApiError> {
+        self.calls
+            .into_values()
+            .map(|call| {
+                let name = call
+                    .name
+                    .ok_or_else(|| ApiError::Parse("Tool call is missing a function name".to_string()))?;
+                let arguments = if call.arguments.trim().is_empty() {
+                    serde_json::json!({})
+                } else {
+                    serde_json::from_str(&call.arguments).map_err(|e| {
+                        ApiError::Parse(format!("Tool call {} has invalid JSON arguments: {}", name, e))
+                    })?
+                };
+
+                Ok(ToolCall {
+                    id: call.id.unwrap_or_else(|| format!("call_{}", uuid::Uuid::new_v4().simple())),
+                    name,
+                    arguments,
+                })
+            })
+            .collect()
+    }
+}
+
+// Where a parked conversation continues
+#[derive(Clone)]
+pub enum ToolRoundOrigin {
+    Provider { config: ProviderConfig, request: ProviderRequest },
+    Extab { request: api::ChatRequest },
+}
+
+#[derive(Clone)]
+pub struct PendingToolRound {
+    origin: ToolRoundOrigin,
+    // The assistant turn that asked for the tools
+    assistant: ChatMessage,
+    tool_calls: Vec<ToolCall>,
+    parked_at: Instant,
+}
+
+impl PendingToolRound {
+    fn is_expired(&self) -> bool {
+        self.parked_at.elapsed() > TOOL_ROUND_TTL
+    }
+}
+
+/// The assistant turn of a finished stream, as a message for the history.
+fn assistant_message(output: &ChatOutput) -> ChatMessage {
+    let mut content = Vec::new();
+    if !output.content.is_empty() {
+        content.push(ContentPart::Text { text: output.content.clone() });
+    }
+    content.extend(output.tool_calls.iter().cloned().map(ContentPart::ToolCall));
+
+    ChatMessage { role: Role::Assistant, content }
+}
+
+/// Parks the conversation if the stream ended waiting on tool results.
+pub(crate) fn park_tool_round(app: &AppHandle, request_id: &str, origin: ToolRoundOrigin, output: &ChatOutput) {
+    if output.tool_calls.is_empty() {
+        return;
+    }
+
+    let round = PendingToolRound {
+        origin,
+        assistant: assistant_message(output),
+        tool_calls: output.tool_calls.clone(),
+        parked_at: Instant::now(),
+    };
+    let state = app.state::<crate::ChatStreamState>();
+    let mut rounds = state.tool_rounds.lock().unwrap();
+    rounds.retain(|_, round| !round.is_expired());
+    rounds.insert(request_id.to_string(), round);
+}
+
+/// Sends tool results for a stream that ended with tool calls and streams
+/// the follow-up under the same request id. If the follow-up fails or is
+/// cancelled the round stays parked, so the same results can be sent again.
+#[tauri::command]
+pub async fn submit_tool_results(
+    app: AppHandle,
+    request_id: String,
+    results: Vec<ToolResult>,
+) -> Result<ChatOutput, ApiError> {
+    let round = app
+        .state::<crate::ChatStreamState>()
+        .tool_rounds
+        .lock()
+        .unwrap()
+        .remove(&request_id)
+        .filter(|round| !round.is_expired())
+        .ok_or_else(|| ApiError::Config(format!("No tool calls are waiting for results on {}", request_id)))?;
+
+    let missing: Vec<&str> = round
+        .tool_calls
+        .iter()
+        .filter(|call| !results.iter().any(|r| r.tool_call_id == call.id))
+        .map(|call| call.id.as_str())
+        .collect();
+    if !missing.is_empty() {
+        let message = format!("Missing results for tool calls: {}", missing.join(", "));
+        // Keep the round so the caller can retry with every result
+        app.state::<crate::ChatStreamState>()
+            .tool_rounds
+            .lock()
+            .unwrap()
+            .insert(request_id, round);
+        return Err(ApiError::Config(message));
+    }
+
+    let tool_message = ChatMessage {
+        role: Role::Tool,
+        content: results.into_iter().map(ContentPart::ToolResult).collect(),
+    };
+    let retry_round = round.clone();
+    let retry_id = request_id.clone();
+
+    let result = match round.origin {
+        ToolRoundOrigin::Provider { config, mut request } => {
+            // Thinking blocks aren't kept, and Anthropic rejects thinking when
+            // the last assistant turn doesn't start with its signed block
+            if config.kind == ProviderKind::Anthropic {
+                request.reasoning.mode = ReasoningMode::Disabled;
+            }
+            request.messages.push(round.assistant);
+            request.messages.push(tool_message);
+            super::run_provider_stream(app.clone(), request_id, config, request).await
+        }
+        ToolRoundOrigin::Extab { request } => {
+            api::continue_extab_stream(app.clone(), request_id, request, &round.assistant, &tool_message).await
+        }
+    };
+
+    if result.is_err() {
+        app.state::<crate::ChatStreamState>()
+            .tool_rounds
+            .lock()
+            .unwrap()
+            .entry(retry_id)
+            .or_insert(PendingToolRound { parked_at: Instant::now(), ..retry_round });
+    }
+    result
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fragment(index: usize, id: Option<&str>, name: Option<&str>, arguments: &str) -> ChatDelta {
+        ChatDelta::ToolCall {
+            index,
+            id: id.map(str::to_string),
+            name: name.map(str::to_string),
+            arguments: arguments.to_string(),
+        }
+    }
+
+    #[test]
+    fn assembles_interleaved_fragments_by_index() {
+        let mut assembler = ToolCallAssembler::default();
+        assembler.push(&fragment(1, Some("call_b"), Some("search"), "{\"q\":"));
+        assembler.push(&fragment(0, Some("call_a"), Some("lookup"), ""));
+        assembler.push(&fragment(0, None, None, "{\"id\": 7}"));
+        let progress = assembler.push(&fragment(1, None, None, " \"rust\"}")).unwrap();
+        assert_eq!(progress.id.as_deref(), Some("call_b"));
+        assert_eq!(progress.arguments_delta, " \"rust\"}");
+        assert_eq!(progress.arguments, "{\"q\": \"rust\"}");
+
+        let calls = assembler.finish().unwrap();
+        assert_eq!(
+            calls,
+            vec![
+                ToolCall { id: "call_a".to_string(), name: "lookup".to_string(), arguments: serde_json::json!({ "id": 7 }) },
+                ToolCall { id: "call_b".to_string(), name: "search".to_string(), arguments: serde_json::json!({ "q": "rust" }) },
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_other_deltas_and_fills_defaults() {
+        let mut assembler = ToolCallAssembler::default();
+        assert!(assembler.push(&ChatDelta::Text { text: "hi".to_string() }).is_none());
+        assembler.push(&fragment(0, None, Some("now"), "  "));
+
+        let calls = assembler.finish().unwrap();
+        assert_eq!(calls[0].arguments, serde_json::json!({}));
+        assert!(calls[0].id.starts_with("call_"));
+    }
+
+    #[test]
+    fn rejects_a_call_without_a_name() {
+        let mut assembler = ToolCallAssembler::default();
+        assembler.push(&fragment(0, Some("call_a"), None, "{}"));
+        assert!(matches!(assembler.finish(), Err(ApiError::Parse(_))));
+    }
+
+    #[test]
+    fn rejects_invalid_json_arguments() {
+        let mut assembler = ToolCallAssembler::default();
+        assembler.push(&fragment(0, Some("call_a"), Some("lookup"), "{\"id\": "));
+        let error = assembler.finish().unwrap_err();
+        assert!(matches!(&error, ApiError::Parse(message) if message.contains("lookup")));
+    }
+}
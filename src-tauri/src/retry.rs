@@ -0,0 +1,183 @@
+// Retry with exponential backoff for backend API requests
+//
+// Only the send and status check are retried. Once a response is handed back
+// the caller owns the body, so streams are never replayed after the first byte.
+
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::error::ApiError;
+use crate::http::HttpClientState;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct RetryPolicy {
+    // Total attempts including the first one, 1 disables retries
+    pub max_attempts: u32,
+    pub base_delay_ms: u64,
+    // Longest wait between attempts, a longer Retry-After fails right away
+    pub max_delay_ms: u64,
+    // No retry is scheduled past this much time since the first attempt
+    pub max_total_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay_ms: 500,
+            max_delay_ms: 8_000,
+            max_total_ms: 20_000,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RetryEvent {
+    pub context: String,
+    // The attempt about to be made, e.g. 2 of 3
+    pub attempt: u32,
+    pub max_attempts: u32,
+    pub delay_ms: u64,
+    pub error: ApiError,
+}
+
+impl RetryPolicy {
+    /// Longest backoff before `attempt`: base * 2^(attempt - 1), capped.
+    fn backoff_cap(&self, attempt: u32) -> u64 {
+        self.base_delay_ms
+            .saturating_mul(1u64 << attempt.saturating_sub(1).min(16))
+            .min(self.max_delay_ms)
+    }
+
+    /// Full-jitter backoff: a random delay up to `backoff_cap`.
+    fn backoff(&self, attempt: u32) -> Duration {
+        Duration::from_millis(rand::thread_rng().gen_range(0..=self.backoff_cap(attempt)))
+    }
+}
+
+// Delay-seconds or an HTTP date, a date already passed means retry now
+fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = DateTime::parse_from_rfc2822(value).ok()?.with_timezone(&Utc);
+    Some((date - now).to_std().unwrap_or(Duration::ZERO))
+}
+
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    let value = response.headers().get(reqwest::header::RETRY_AFTER)?.to_str().ok()?;
+    parse_retry_after(value, Utc::now())
+}
+
+/// Sends the request built by `build`, retrying transient failures per the
+/// configured policy and emitting `api_retry` before each new attempt.
+/// A 304 is handed back like a success, for callers sending conditional requests.
+pub async fn send_with_retry<F>(app: &AppHandle, context: &str, build: F) -> Result<reqwest::Response, ApiError>
+where
+    F: Fn() -> reqwest::RequestBuilder,
+{
+    let policy = app.state::<HttpClientState>().settings().retry;
+    let max_attempts = policy.max_attempts.max(1);
+    let started = Instant::now();
+    let mut attempt = 1;
+
+    loop {
+        let (error, server_delay) = match build().send().await {
+            Ok(response)
+                if response.status().is_success() || response.status() == reqwest::StatusCode::NOT_MODIFIED =>
+            {
+                return Ok(response)
+            }
+            Ok(response) => {
+                let delay = retry_after(&response);
+                let error = ApiError::check_response(response)
+                    .await
+                    .err()
+                    .unwrap_or_else(|| ApiError::Network("Unexpected response".to_string()));
+                (error, delay)
+            }
+            Err(e) => (ApiError::from_reqwest(context, e), None),
+        };
+
+        if !error.is_retryable() || attempt >= max_attempts {
+            return Err(error);
+        }
+
+        let delay = match server_delay {
+            // Retrying before the server's Retry-After only earns a longer ban,
+            // so a wait past the cap fails now instead
+            Some(delay) if delay > Duration::from_millis(policy.max_delay_ms) => return Err(error),
+            Some(delay) => delay,
+            None => policy.backoff(attempt),
+        };
+        if started.elapsed() + delay > Duration::from_millis(policy.max_total_ms) {
+            return Err(error);
+        }
+
+        attempt += 1;
+        let event = RetryEvent {
+            context: context.to_string(),
+            attempt,
+            max_attempts,
+            delay_ms: delay.as_millis() as u64,
+            error,
+        };
+        let _ = app.emit("api_retry", event);
+
+        tokio::time::sleep(delay).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy { base_delay_ms: 500, max_delay_ms: 8_000, ..RetryPolicy::default() }
+    }
+
+    #[test]
+    fn backoff_cap_doubles_up_to_the_maximum() {
+        let policy = policy();
+        let caps: Vec<u64> = (1..=7).map(|attempt| policy.backoff_cap(attempt)).collect();
+        assert_eq!(caps, vec![500, 1_000, 2_000, 4_000, 8_000, 8_000, 8_000]);
+        // Large attempt numbers don't overflow the shift
+        assert_eq!(policy.backoff_cap(u32::MAX), 8_000);
+    }
+
+    #[test]
+    fn backoff_jitter_stays_within_the_cap() {
+        let policy = policy();
+        for attempt in 1..=6 {
+            let cap = Duration::from_millis(policy.backoff_cap(attempt));
+            for _ in 0..200 {
+                assert!(policy.backoff(attempt) <= cap);
+            }
+        }
+        let no_delay = RetryPolicy { base_delay_ms: 0, ..policy };
+        assert_eq!(no_delay.backoff(3), Duration::ZERO);
+    }
+
+    #[test]
+    fn retry_after_seconds() {
+        let now = Utc::now();
+        assert_eq!(parse_retry_after("30", now), Some(Duration::from_secs(30)));
+        assert_eq!(parse_retry_after(" 0 ", now), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("-5", now), None);
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn retry_after_http_date() {
+        let now = DateTime::parse_from_rfc2822("Wed, 21 Oct 2015 07:28:00 GMT").unwrap().with_timezone(&Utc);
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:45 GMT", now), Some(Duration::from_secs(45)));
+        // Already passed
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:00:00 GMT", now), Some(Duration::ZERO));
+    }
+}
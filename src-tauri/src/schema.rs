@@ -0,0 +1,466 @@
+// JSON Schema validation for structured model output
+//
+// Covers the keywords structured output schemas use in practice: types, enum
+// and const, object properties, required and additionalProperties, array
+// items and prefixItems, string and number bounds, patterns, the allOf /
+// anyOf / oneOf / not combinators and local `#/...` references. Formats and
+// remote references are not checked. Patterns use the Rust regex syntax, so
+// ECMA-only features like lookaround are rejected with the schema.
+
+use regex::Regex;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+// Enough for a repair prompt, more only adds noise
+const MAX_ERRORS: usize = 20;
+
+/// Checks that `schema` can be used at all, before asking a model for output.
+pub fn check_schema(schema: &Value) -> Result<(), String> {
+    if !schema.is_object() {
+        return Err("Schema must be a JSON object".to_string());
+    }
+    compile_patterns(schema).map(|_| ())
+}
+
+/// Validates `instance` against `schema`. Errors read "<path>: <problem>",
+/// with the path as a JSON pointer.
+pub fn validate(schema: &Value, instance: &Value) -> Result<(), Vec<String>> {
+    let patterns = compile_patterns(schema).map_err(|e| vec![e])?;
+    let mut validator = Validator { root: schema, patterns: &patterns, refs: Vec::new(), errors: Vec::new() };
+    validator.check(schema, instance, "");
+
+    if validator.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(validator.errors)
+    }
+}
+
+struct Validator<'a> {
+    root: &'a Value,
+    // Every `pattern` in the schema, compiled once
+    patterns: &'a HashMap<String, Regex>,
+    // References being followed, with the path they were followed at
+    refs: Vec<(String, String)>,
+    errors: Vec<String>,
+}
+
+/// The schemas nested directly in `schema`, wherever a keyword takes one.
+fn sub_schemas(schema: &Map<String, Value>) -> Vec<&Value> {
+    let mut nested = Vec::new();
+    for key in ["properties", "$defs", "definitions"] {
+        nested.extend(schema.get(key).and_then(Value::as_object).into_iter().flat_map(Map::values));
+    }
+    for key in ["prefixItems", "allOf", "anyOf", "oneOf"] {
+        nested.extend(schema.get(key).and_then(Value::as_array).into_iter().flatten());
+    }
+    for key in ["items", "additionalProperties", "not"] {
+        nested.extend(schema.get(key));
+    }
+    nested
+}
+
+fn compile_patterns(schema: &Value) -> Result<HashMap<String, Regex>, String> {
+    let mut patterns = HashMap::new();
+    let mut pending = vec![schema];
+    while let Some(schema) = pending.pop() {
+        let Value::Object(schema) = schema else {
+            continue;
+        };
+        if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+            if !patterns.contains_key(pattern) {
+                let regex = Regex::new(pattern).map_err(|e| format!("Schema pattern {} is invalid: {}", pattern, e))?;
+                patterns.insert(pattern.to_string(), regex);
+            }
+        }
+        pending.extend(sub_schemas(schema));
+    }
+    Ok(patterns)
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn matches_type(value: &Value, expected: &str) -> bool {
+    match expected {
+        "integer" => value.as_f64().is_some_and(|n| n.fract() == 0.0),
+        "number" => value.is_number(),
+        other => type_name(value) == other,
+    }
+}
+
+fn display_path(path: &str) -> &str {
+    if path.is_empty() {
+        "/"
+    } else {
+        path
+    }
+}
+
+// JSON pointer escaping for property names
+fn child_path(path: &str, key: &str) -> String {
+    format!("{}/{}", path, key.replace('~', "~0").replace('/', "~1"))
+}
+
+impl Validator<'_> {
+    fn error(&mut self, path: &str, message: String) {
+        if self.errors.len() < MAX_ERRORS {
+            self.errors.push(format!("{}: {}", display_path(path), message));
+        }
+    }
+
+    /// Whether `value` matches without recording errors, for the combinators.
+    fn is_valid(&self, schema: &Value, value: &Value, path: &str) -> bool {
+        let mut probe = Validator {
+            root: self.root,
+            patterns: self.patterns,
+            refs: self.refs.clone(),
+            errors: Vec::new(),
+        };
+        probe.check(schema, value, path);
+        probe.errors.is_empty()
+    }
+
+    fn check(&mut self, schema: &Value, value: &Value, path: &str) {
+        let schema = match schema {
+            Value::Bool(true) => return,
+            Value::Bool(false) => return self.error(path, "no value is allowed here".to_string()),
+            Value::Object(schema) => schema,
+            _ => return,
+        };
+
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            // Reaching the same reference again without moving into the value
+            // would recurse forever
+            let visit = (reference.to_string(), path.to_string());
+            if self.refs.contains(&visit) {
+                return self.error(path, format!("reference {} refers back to itself", reference));
+            }
+            match reference.strip_prefix('#').and_then(|pointer| self.root.pointer(pointer)) {
+                Some(target) => {
+                    self.refs.push(visit);
+                    self.check(target, value, path);
+                    self.refs.pop();
+                }
+                None => self.error(path, format!("unresolvable reference {}", reference)),
+            }
+        }
+
+        if let Some(expected) = schema.get("type") {
+            let allowed: Vec<&str> = match expected {
+                Value::String(t) => vec![t.as_str()],
+                Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+                _ => Vec::new(),
+            };
+            if !allowed.is_empty() && !allowed.iter().any(|t| matches_type(value, t)) {
+                return self.error(path, format!("expected {}, found {}", allowed.join(" or "), type_name(value)));
+            }
+        }
+
+        if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+            if !options.contains(value) {
+                self.error(path, format!("{} is not one of {}", value, Value::Array(options.clone())));
+            }
+        }
+        if let Some(expected) = schema.get("const") {
+            if expected != value {
+                self.error(path, format!("expected {}", expected));
+            }
+        }
+
+        match value {
+            Value::String(text) => self.check_string(schema, text, path),
+            Value::Number(_) => self.check_number(schema, value.as_f64().unwrap_or_default(), path),
+            Value::Array(items) => self.check_array(schema, items, path),
+            Value::Object(object) => self.check_object(schema, object, path),
+            _ => {}
+        }
+
+        self.check_combinators(schema, value, path);
+    }
+
+    fn check_string(&mut self, schema: &Map<String, Value>, text: &str, path: &str) {
+        let length = text.chars().count() as u64;
+        if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+            if length < min {
+                self.error(path, format!("must be at least {} characters", min));
+            }
+        }
+        if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+            if length > max {
+                self.error(path, format!("must be at most {} characters", max));
+            }
+        }
+        if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+            if self.patterns.get(pattern).is_some_and(|regex| !regex.is_match(text)) {
+                self.error(path, format!("does not match pattern {}", pattern));
+            }
+        }
+    }
+
+    fn check_number(&mut self, schema: &Map<String, Value>, number: f64, path: &str) {
+        let bound = |key: &str| schema.get(key).and_then(Value::as_f64);
+
+        if let Some(min) = bound("minimum").filter(|min| number < *min) {
+            self.error(path, format!("must be at least {}", min));
+        }
+        if let Some(max) = bound("maximum").filter(|max| number > *max) {
+            self.error(path, format!("must be at most {}", max));
+        }
+        if let Some(min) = bound("exclusiveMinimum").filter(|min| number <= *min) {
+            self.error(path, format!("must be greater than {}", min));
+        }
+        if let Some(max) = bound("exclusiveMaximum").filter(|max| number >= *max) {
+            self.error(path, format!("must be less than {}", max));
+        }
+        if let Some(step) = bound("multipleOf").filter(|step| *step > 0.0) {
+            let quotient = number / step;
+            if (quotient - quotient.round()).abs() > 1e-9 {
+                self.error(path, format!("must be a multiple of {}", step));
+            }
+        }
+    }
+
+    fn check_array(&mut self, schema: &Map<String, Value>, items: &[Value], path: &str) {
+        let count = items.len() as u64;
+        if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+            if count < min {
+                self.error(path, format!("must have at least {} items", min));
+            }
+        }
+        if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+            if count > max {
+                self.error(path, format!("must have at most {} items", max));
+            }
+        }
+        if schema.get("uniqueItems").and_then(Value::as_bool) == Some(true) {
+            let duplicate = items.iter().enumerate().any(|(i, item)| items[..i].contains(item));
+            if duplicate {
+                self.error(path, "items must be unique".to_string());
+            }
+        }
+
+        let prefix = schema.get("prefixItems").and_then(Value::as_array).map_or(&[][..], Vec::as_slice);
+        for (index, item) in items.iter().enumerate() {
+            let item_path = format!("{}/{}", path, index);
+            match (prefix.get(index), schema.get("items")) {
+                (Some(item_schema), _) => self.check(item_schema, item, &item_path),
+                (None, Some(item_schema)) => self.check(item_schema, item, &item_path),
+                (None, None) => {}
+            }
+        }
+    }
+
+    fn check_object(&mut self, schema: &Map<String, Value>, object: &Map<String, Value>, path: &str) {
+        for name in schema.get("required").and_then(Value::as_array).into_iter().flatten() {
+            if let Some(name) = name.as_str().filter(|name| !object.contains_key(*name)) {
+                self.error(path, format!("missing required property \"{}\"", name));
+            }
+        }
+
+        let count = object.len() as u64;
+        if let Some(min) = schema.get("minProperties").and_then(Value::as_u64) {
+            if count < min {
+                self.error(path, format!("must have at least {} properties", min));
+            }
+        }
+        if let Some(max) = schema.get("maxProperties").and_then(Value::as_u64) {
+            if count > max {
+                self.error(path, format!("must have at most {} properties", max));
+            }
+        }
+
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for (key, value) in object {
+            let key_path = child_path(path, key);
+            match (properties.and_then(|p| p.get(key)), schema.get("additionalProperties")) {
+                (Some(property), _) => self.check(property, value, &key_path),
+                (None, Some(Value::Bool(false))) => self.error(&key_path, "property is not allowed".to_string()),
+                (None, Some(additional)) => self.check(additional, value, &key_path),
+                (None, None) => {}
+            }
+        }
+    }
+
+    fn check_combinators(&mut self, schema: &Map<String, Value>, value: &Value, path: &str) {
+        for sub_schema in schema.get("allOf").and_then(Value::as_array).into_iter().flatten() {
+            self.check(sub_schema, value, path);
+        }
+
+        if let Some(options) = schema.get("anyOf").and_then(Value::as_array) {
+            if !options.iter().any(|option| self.is_valid(option, value, path)) {
+                self.error(path, "does not match any of the allowed schemas".to_string());
+            }
+        }
+
+        if let Some(options) = schema.get("oneOf").and_then(Value::as_array) {
+            let matched = options.iter().filter(|option| self.is_valid(option, value, path)).count();
+            if matched != 1 {
+                self.error(path, format!("must match exactly one of the allowed schemas, matched {}", matched));
+            }
+        }
+
+        if let Some(not) = schema.get("not") {
+            if self.is_valid(not, value, path) {
+                self.error(path, "matches a schema it must not match".to_string());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn errors(schema: Value, instance: Value) -> Vec<String> {
+        validate(&schema, &instance).err().unwrap_or_default()
+    }
+
+    #[test]
+    fn check_schema_requires_an_object_with_valid_patterns() {
+        assert!(check_schema(&json!({ "type": "string" })).is_ok());
+        assert!(check_schema(&json!(true)).is_err());
+        assert!(check_schema(&json!("object")).is_err());
+
+        // Lookaround is valid ECMA but not Rust regex syntax
+        let nested = json!({
+            "type": "object",
+            "properties": { "code": { "type": "string", "pattern": "^(?!x)" } },
+        });
+        assert!(check_schema(&nested).unwrap_err().contains("pattern"));
+        let under_defs = json!({ "$defs": { "a": { "anyOf": [{ "pattern": "(" }] } } });
+        assert!(check_schema(&under_defs).is_err());
+        // A property named "pattern" isn't a keyword
+        assert!(check_schema(&json!({ "properties": { "pattern": { "type": "string" } } })).is_ok());
+    }
+
+    #[test]
+    fn types_and_integers() {
+        assert!(errors(json!({ "type": "integer" }), json!(3)).is_empty());
+        assert!(errors(json!({ "type": "integer" }), json!(3.0)).is_empty());
+        assert_eq!(errors(json!({ "type": "integer" }), json!(3.5)), vec!["/: expected integer, found number"]);
+        assert!(errors(json!({ "type": "number" }), json!(3)).is_empty());
+        assert!(errors(json!({ "type": ["string", "null"] }), json!(null)).is_empty());
+        assert_eq!(errors(json!({ "type": "string" }), json!([])), vec!["/: expected string, found array"]);
+    }
+
+    #[test]
+    fn required_and_additional_properties() {
+        let schema = json!({
+            "type": "object",
+            "properties": { "name": { "type": "string" }, "a/b": { "type": "integer" } },
+            "required": ["name"],
+            "additionalProperties": false,
+        });
+        assert!(errors(schema.clone(), json!({ "name": "x", "a/b": 1 })).is_empty());
+        assert_eq!(
+            errors(schema.clone(), json!({ "a/b": "one", "extra": 1 })),
+            vec![
+                "/: missing required property \"name\"",
+                "/a~1b: expected integer, found string",
+                "/extra: property is not allowed",
+            ]
+        );
+
+        let typed_extra = json!({ "additionalProperties": { "type": "boolean" } });
+        assert_eq!(errors(typed_extra, json!({ "flag": 1 })), vec!["/flag: expected boolean, found integer"]);
+    }
+
+    #[test]
+    fn prefix_items_then_items() {
+        let schema = json!({
+            "type": "array",
+            "prefixItems": [{ "type": "string" }, { "type": "integer" }],
+            "items": { "type": "boolean" },
+            "maxItems": 4,
+        });
+        assert!(errors(schema.clone(), json!(["a", 1, true])).is_empty());
+        assert_eq!(
+            errors(schema, json!([1, 1, "no", true, false])),
+            vec![
+                "/: must have at most 4 items",
+                "/0: expected string, found integer",
+                "/2: expected boolean, found string",
+            ]
+        );
+    }
+
+    #[test]
+    fn string_bounds_and_patterns() {
+        let schema = json!({ "type": "string", "minLength": 2, "pattern": "^[a-z]+$" });
+        assert!(errors(schema.clone(), json!("ab")).is_empty());
+        assert_eq!(
+            errors(schema, json!("A")),
+            vec!["/: must be at least 2 characters", "/: does not match pattern ^[a-z]+$"]
+        );
+    }
+
+    #[test]
+    fn combinators() {
+        let any_of = json!({ "anyOf": [{ "type": "string" }, { "type": "integer" }] });
+        assert!(errors(any_of.clone(), json!(1)).is_empty());
+        assert_eq!(errors(any_of, json!(null)), vec!["/: does not match any of the allowed schemas"]);
+
+        let one_of = json!({ "oneOf": [{ "type": "number" }, { "type": "integer" }] });
+        assert!(errors(one_of.clone(), json!(1.5)).is_empty());
+        assert_eq!(errors(one_of, json!(1)), vec!["/: must match exactly one of the allowed schemas, matched 2"]);
+
+        let not = json!({ "not": { "const": "forbidden" } });
+        assert!(errors(not.clone(), json!("fine")).is_empty());
+        assert_eq!(errors(not, json!("forbidden")), vec!["/: matches a schema it must not match"]);
+
+        let all_of = json!({ "allOf": [{ "minimum": 2 }, { "maximum": 3 }] });
+        assert_eq!(errors(all_of, json!(5)), vec!["/: must be at most 3"]);
+    }
+
+    #[test]
+    fn references() {
+        let schema = json!({
+            "$defs": {
+                "node": {
+                    "type": "object",
+                    "properties": {
+                        "value": { "type": "integer" },
+                        "children": { "type": "array", "items": { "$ref": "#/$defs/node" } },
+                    },
+                    "required": ["value"],
+                },
+            },
+            "$ref": "#/$defs/node",
+        });
+        let tree = json!({ "value": 1, "children": [{ "value": 2, "children": [{ "value": "3" }] }] });
+        assert_eq!(errors(schema, tree), vec!["/children/0/children/0/value: expected integer, found string"]);
+
+        assert_eq!(errors(json!({ "$ref": "#/$defs/missing" }), json!(1)), vec!["/: unresolvable reference #/$defs/missing"]);
+    }
+
+    #[test]
+    fn reference_cycles_are_reported() {
+        assert_eq!(errors(json!({ "$ref": "#" }), json!(1)), vec!["/: reference # refers back to itself"]);
+
+        let mutual = json!({
+            "$defs": { "a": { "$ref": "#/$defs/b" }, "b": { "anyOf": [{ "$ref": "#/$defs/a" }] } },
+            "$ref": "#/$defs/a",
+        });
+        assert!(!errors(mutual, json!({})).is_empty());
+    }
+
+    #[test]
+    fn errors_are_capped() {
+        let schema = json!({ "type": "array", "items": { "type": "string" } });
+        let instance = Value::Array((0..50).map(|i| json!(i)).collect());
+        let errors = errors(schema, instance);
+        assert_eq!(errors.len(), MAX_ERRORS);
+        assert_eq!(errors[0], "/0: expected string, found integer");
+    }
+}
@@ -0,0 +1,325 @@
+// Incremental Server-Sent Events decoder shared by the streaming providers
+//
+// Works on raw bytes and only decodes complete lines, so multibyte characters
+// split across network chunks survive. Follows the WHATWG event stream rules:
+// CR, LF and CRLF line endings, `:` comments, `event:` / `id:` fields and
+// multi-line `data:` joined with newlines.
+
+use futures_util::{Stream, StreamExt};
+use std::collections::VecDeque;
+use std::pin::Pin;
+
+use crate::error::ApiError;
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SseEvent {
+    // `None` means the default "message" type
+    pub event: Option<String>,
+    pub data: String,
+    pub id: Option<String>,
+}
+
+impl SseEvent {
+    /// OpenAI-style end of stream marker.
+    pub fn is_done(&self) -> bool {
+        self.data.trim() == "[DONE]"
+    }
+
+    pub fn json(&self) -> Option<serde_json::Value> {
+        serde_json::from_str(&self.data).ok()
+    }
+
+    /// Message of an error sent mid-stream, either as an `error` event or as
+    /// a JSON payload with an `error` field.
+    pub fn error_message(&self) -> Option<String> {
+        let json = self.json();
+        // Some providers send `"error": null` on every normal chunk
+        let error = json.as_ref().and_then(|j| j.get("error")).filter(|e| !e.is_null());
+
+        if self.event.as_deref() != Some("error") && error.is_none() {
+            return None;
+        }
+
+        let message = error
+            .and_then(|e| e.as_str().or_else(|| e.get("message").and_then(|m| m.as_str())))
+            .or_else(|| json.as_ref().and_then(|j| j.get("message")).and_then(|m| m.as_str()))
+            .map(|m| m.to_string())
+            .unwrap_or_else(|| self.data.clone());
+        Some(message)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SseDecoder {
+    // Bytes of the line currently being received
+    line: Vec<u8>,
+    // Last byte was a CR, so a following LF belongs to the same line ending
+    after_cr: bool,
+    started: bool,
+    event: Option<String>,
+    data: String,
+    id: Option<String>,
+}
+
+impl SseDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds a network chunk and returns the events it completed.
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<SseEvent> {
+        let mut events = Vec::new();
+
+        for &byte in bytes {
+            match byte {
+                b'\n' if self.after_cr => self.after_cr = false,
+                b'\r' | b'\n' => {
+                    self.after_cr = byte == b'\r';
+                    let line = std::mem::take(&mut self.line);
+                    if let Some(event) = self.process_line(&line) {
+                        events.push(event);
+                    }
+                }
+                _ => {
+                    self.after_cr = false;
+                    self.line.push(byte);
+                }
+            }
+        }
+
+        events
+    }
+
+    /// Flushes at end of stream. Unlike the spec, an event missing its final
+    /// blank line is still dispatched since some servers omit it.
+    pub fn finish(&mut self) -> Vec<SseEvent> {
+        let mut events = Vec::new();
+
+        if !self.line.is_empty() {
+            let line = std::mem::take(&mut self.line);
+            events.extend(self.process_line(&line));
+        }
+        events.extend(self.process_line(b""));
+        events
+    }
+
+    fn process_line(&mut self, line: &[u8]) -> Option<SseEvent> {
+        let mut line = line;
+        if !self.started {
+            self.started = true;
+            line = line.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(line);
+        }
+
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(b":") {
+            return None;
+        }
+
+        let line = String::from_utf8_lossy(line);
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line.as_ref(), ""),
+        };
+
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => {
+                self.data.push_str(value);
+                self.data.push('\n');
+            }
+            "id" if !value.contains('\0') => self.id = Some(value.to_string()),
+            // `retry` only matters for EventSource reconnection
+            _ => {}
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let event = self.event.take();
+        if self.data.is_empty() {
+            return None;
+        }
+
+        let mut data = std::mem::take(&mut self.data);
+        data.pop();
+        Some(SseEvent {
+            event,
+            data,
+            id: self.id.clone(),
+        })
+    }
+}
+
+/// Decodes a response body into events. Ends cleanly at `[DONE]` and turns
+/// mid-stream error payloads into errors.
+pub struct SseStream<B> {
+    inner: Pin<Box<dyn Stream<Item = Result<B, reqwest::Error>> + Send>>,
+    decoder: SseDecoder,
+    pending: VecDeque<SseEvent>,
+    finished: bool,
+}
+
+impl<B: AsRef<[u8]>> SseStream<B> {
+    pub fn new(inner: impl Stream<Item = Result<B, reqwest::Error>> + Send + 'static) -> Self {
+        SseStream {
+            inner: Box::pin(inner),
+            decoder: SseDecoder::new(),
+            pending: VecDeque::new(),
+            finished: false,
+        }
+    }
+
+    /// Next data event, `None` once the stream is done.
+    pub async fn next_event(&mut self) -> Result<Option<SseEvent>, ApiError> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                if event.is_done() {
+                    self.finished = true;
+                    self.pending.clear();
+                    return Ok(None);
+                }
+                if let Some(message) = event.error_message() {
+                    self.finished = true;
+                    self.pending.clear();
+                    return Err(ApiError::Stream(format!("Stream error: {}", message)));
+                }
+                return Ok(Some(event));
+            }
+
+            if self.finished {
+                return Ok(None);
+            }
+
+            match self.inner.next().await {
+                Some(Ok(bytes)) => self.pending.extend(self.decoder.push(bytes.as_ref())),
+                Some(Err(e)) => {
+                    self.finished = true;
+                    return Err(ApiError::Stream(format!("Stream error: {}", e.without_url())));
+                }
+                None => {
+                    self.finished = true;
+                    self.pending.extend(self.decoder.finish());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_chunks(chunks: &[&[u8]]) -> Vec<SseEvent> {
+        let mut decoder = SseDecoder::new();
+        let mut events: Vec<SseEvent> = chunks.iter().flat_map(|c| decoder.push(c)).collect();
+        events.extend(decoder.finish());
+        events
+    }
+
+    fn data(events: &[SseEvent]) -> Vec<&str> {
+        events.iter().map(|e| e.data.as_str()).collect()
+    }
+
+    #[test]
+    fn utf8_split_across_chunks() {
+        let bytes = "data: héllo 🌍\n\n".as_bytes();
+        // Split inside both the two byte and the four byte sequences
+        let events = decode_chunks(&[&bytes[..8], &bytes[8..14], &bytes[14..]]);
+        assert_eq!(data(&events), vec!["héllo 🌍"]);
+    }
+
+    #[test]
+    fn event_and_id_fields() {
+        let events = decode_chunks(&[b"event: content_block_delta\nid: 7\ndata: {}\n\n"]);
+        assert_eq!(events[0].event.as_deref(), Some("content_block_delta"));
+        assert_eq!(events[0].id.as_deref(), Some("7"));
+        assert_eq!(events[0].data, "{}");
+    }
+
+    #[test]
+    fn event_type_resets_between_events() {
+        let events = decode_chunks(&[b"event: ping\ndata: a\n\ndata: b\n\n"]);
+        assert_eq!(events[0].event.as_deref(), Some("ping"));
+        assert_eq!(events[1].event, None);
+    }
+
+    #[test]
+    fn multi_line_data() {
+        let events = decode_chunks(&[b"data: first\ndata:second\ndata\n\n"]);
+        assert_eq!(data(&events), vec!["first\nsecond\n"]);
+    }
+
+    #[test]
+    fn crlf_and_cr_line_endings() {
+        let events = decode_chunks(&[b"data: a\r\n\r\ndata: b\r\rdata: c\n\n"]);
+        assert_eq!(data(&events), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn crlf_split_across_chunks() {
+        let events = decode_chunks(&[b"data: a\r", b"\n\r", b"\ndata: b\r\n\r\n"]);
+        assert_eq!(data(&events), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn comments_and_empty_events_are_skipped() {
+        let events = decode_chunks(&[b": keep-alive\n\nevent: ping\n\ndata: x\n\n"]);
+        assert_eq!(data(&events), vec!["x"]);
+    }
+
+    #[test]
+    fn leading_bom_is_ignored() {
+        let events = decode_chunks(&[b"\xEF\xBB\xBFdata: x\n\n"]);
+        assert_eq!(data(&events), vec!["x"]);
+    }
+
+    #[test]
+    fn unterminated_event_is_flushed_at_end() {
+        let events = decode_chunks(&[b"data: a\n\ndata: tail"]);
+        assert_eq!(data(&events), vec!["a", "tail"]);
+    }
+
+    #[test]
+    fn error_payloads() {
+        let openai = decode_chunks(&[b"data: {\"error\":{\"message\":\"overloaded\"}}\n\n"]);
+        assert_eq!(openai[0].error_message().as_deref(), Some("overloaded"));
+
+        let anthropic = decode_chunks(&[
+            b"event: error\ndata: {\"type\":\"error\",\"error\":{\"type\":\"overloaded_error\",\"message\":\"Overloaded\"}}\n\n",
+        ]);
+        assert_eq!(anthropic[0].error_message().as_deref(), Some("Overloaded"));
+
+        let plain = decode_chunks(&[b"event: error\ndata: boom\n\n"]);
+        assert_eq!(plain[0].error_message().as_deref(), Some("boom"));
+
+        let ok = decode_chunks(&[b"data: {\"choices\":[]}\n\n"]);
+        assert_eq!(ok[0].error_message(), None);
+
+        let null_error = decode_chunks(&[b"data: {\"choices\":[],\"error\":null}\n\n"]);
+        assert_eq!(null_error[0].error_message(), None);
+    }
+
+    fn byte_stream(chunks: &[&'static [u8]]) -> impl Stream<Item = Result<&'static [u8], reqwest::Error>> {
+        futures_util::stream::iter(chunks.iter().map(|c| Ok(*c)).collect::<Vec<_>>())
+    }
+
+    #[tokio::test]
+    async fn done_ends_the_stream() {
+        let mut stream = SseStream::new(byte_stream(&[b"data: a\n\ndata: [DONE]\n\ndata: after\n\n"]));
+        assert_eq!(stream.next_event().await.unwrap().unwrap().data, "a");
+        assert_eq!(stream.next_event().await.unwrap(), None);
+        assert_eq!(stream.next_event().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn mid_stream_error_is_returned() {
+        let mut stream = SseStream::new(byte_stream(&[b"data: a\n\n", b"data: {\"error\":\"quota\"}\n\n"]));
+        assert_eq!(stream.next_event().await.unwrap().unwrap().data, "a");
+        let error = stream.next_event().await.unwrap_err();
+        assert!(error.message().contains("quota"));
+        assert!(!error.is_retryable());
+        assert_eq!(stream.next_event().await.unwrap(), None);
+    }
+}
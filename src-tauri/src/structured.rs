@@ -0,0 +1,197 @@
+// Structured JSON output
+// Asks for JSON matching the caller's JSON Schema, through the provider's
+// structured output mode where it has one, and validates the answer here.
+// An answer that fails validation gets a repair turn quoting the errors
+// before the command gives up.
+
+use serde_json::Value;
+use tauri::AppHandle;
+
+use crate::api::{self, ChatInput};
+use crate::error::ApiError;
+use crate::providers::{
+    self, ChatMessage, ChatOutput, ContentPart, GenerationOptions, JsonSchemaFormat, ProviderConfig, ResponseFormat,
+    Role,
+};
+use crate::schema;
+
+// Repair turns after the first answer
+const MAX_REPAIR_ATTEMPTS: usize = 2;
+const DEFAULT_SCHEMA_NAME: &str = "response";
+
+// Also sent to providers with a structured mode, it costs little and helps
+// models that only get a JSON mime type
+fn schema_prompt(schema: &Value) -> String {
+    format!(
+        "Respond only with a JSON value matching this JSON Schema, without code fences or any other text:\n{}",
+        schema
+    )
+}
+
+fn repair_prompt(errors: &[String]) -> String {
+    format!(
+        "Your last response did not match the schema:\n- {}\nReply with only the corrected JSON.",
+        errors.join("\n- ")
+    )
+}
+
+/// Parses an answer, tolerating a fenced block or text around the JSON.
+fn extract_json(text: &str) -> Result<Value, String> {
+    let text = text.trim();
+    let unfenced = text
+        .strip_prefix("```")
+        .and_then(|rest| rest.strip_suffix("```"))
+        .map(|inner| {
+            // Language tag, models write it in any case
+            let tagged = inner.get(..4).is_some_and(|tag| tag.eq_ignore_ascii_case("json"));
+            if tagged { &inner[4..] } else { inner }.trim()
+        })
+        .unwrap_or(text);
+    if let Ok(value) = serde_json::from_str(unfenced) {
+        return Ok(value);
+    }
+
+    // Otherwise the outermost object or array
+    let start = unfenced.find(['{', '[']);
+    let end = unfenced.rfind(['}', ']']);
+    match (start, end) {
+        (Some(start), Some(end)) if start < end => serde_json::from_str(&unfenced[start..=end])
+            .map_err(|e| format!("Response is not valid JSON: {}", e)),
+        _ => Err("Response does not contain JSON".to_string()),
+    }
+}
+
+fn check_answer(schema: &Value, output: &ChatOutput) -> Result<Value, Vec<String>> {
+    let value = extract_json(&output.content).map_err(|e| vec![e])?;
+    schema::validate(schema, &value)?;
+    Ok(value)
+}
+
+// Repairs stream under their own id so a listener can tell them from the
+// first answer instead of seeing the chunks run together
+fn attempt_id(request_id: &str, attempt: usize) -> String {
+    match attempt {
+        0 => request_id.to_string(),
+        n => format!("{}:repair-{}", request_id, n),
+    }
+}
+
+async fn complete(
+    app: &AppHandle,
+    request_id: &str,
+    provider: Option<&ProviderConfig>,
+    input: ChatInput,
+) -> Result<ChatOutput, ApiError> {
+    match provider {
+        Some(config) => providers::run_chat_input(app.clone(), request_id.to_string(), config.clone(), input).await,
+        None => api::run_chat_input(app.clone(), request_id.to_string(), input).await,
+    }
+}
+
+/// Asks for an answer matching `schema` and returns it parsed and validated.
+/// Goes to the Extab backend, or straight to `provider` when one is given.
+/// The first attempt streams under `request_id` like `chat_stream`, repair
+/// turns under "<request_id>:repair-<n>" counting from 1, and cancelling
+/// `request_id` stops either. Fails with a `validation` error when no
+/// attempt matched.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn structured_output(
+    app: AppHandle,
+    user_message: String,
+    schema: Value,
+    schema_name: Option<String>,
+    system_prompt: Option<String>,
+    image_base64: Option<Value>,
+    history: Option<Vec<ChatMessage>>,
+    provider: Option<ProviderConfig>,
+    options: Option<GenerationOptions>,
+    request_id: Option<String>,
+) -> Result<Value, ApiError> {
+    schema::check_schema(&schema).map_err(ApiError::Config)?;
+    let request_id = request_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    let mut generation = options.unwrap_or_default();
+    generation.response_format = Some(ResponseFormat::JsonSchema {
+        json_schema: JsonSchemaFormat {
+            name: schema_name.unwrap_or_else(|| DEFAULT_SCHEMA_NAME.to_string()),
+            schema: schema.clone(),
+            strict: None,
+        },
+    });
+    let system_prompt = match system_prompt.filter(|p| !p.trim().is_empty()) {
+        Some(prompt) => format!("{}\n\n{}", prompt, schema_prompt(&schema)),
+        None => schema_prompt(&schema),
+    };
+
+    let mut input = ChatInput {
+        user_message,
+        system_prompt: Some(system_prompt),
+        image_base64,
+        history: history.unwrap_or_default(),
+        generation,
+        ..ChatInput::default()
+    };
+
+    let mut attempt = 0;
+    loop {
+        let stream_id = attempt_id(&request_id, attempt);
+        let output = complete(&app, &stream_id, provider.as_ref(), input.clone()).await?;
+        let errors = match check_answer(&schema, &output) {
+            Ok(value) => return Ok(value),
+            Err(errors) => errors,
+        };
+
+        if attempt == MAX_REPAIR_ATTEMPTS {
+            return Err(ApiError::Validation(format!(
+                "Response did not match the schema after {} attempts: {}",
+                attempt + 1,
+                errors.join("; ")
+            )));
+        }
+        attempt += 1;
+
+        // The failed exchange becomes history for the repair turn
+        let user = api::user_turn(&input.user_message, input.image_base64.take().as_ref());
+        input.history.push(user);
+        input.history.push(ChatMessage {
+            role: Role::Assistant,
+            content: vec![ContentPart::Text { text: output.content }],
+        });
+        input.user_message = repair_prompt(&errors);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn repairs_stream_under_derived_ids() {
+        assert_eq!(attempt_id("abc", 0), "abc");
+        assert_eq!(attempt_id("abc", 1), "abc:repair-1");
+        assert_eq!(attempt_id("abc", 2), "abc:repair-2");
+    }
+
+    #[test]
+    fn plain_and_fenced_answers() {
+        assert_eq!(extract_json(" {\"a\": 1} ").unwrap(), json!({ "a": 1 }));
+        assert_eq!(extract_json("```json\n{\"a\": 1}\n```").unwrap(), json!({ "a": 1 }));
+        assert_eq!(extract_json("```JSON\n[1, 2]\n```").unwrap(), json!([1, 2]));
+        assert_eq!(extract_json("```\n{\"a\": true}\n```").unwrap(), json!({ "a": true }));
+    }
+
+    #[test]
+    fn prose_wrapped_answers() {
+        let answer = "Here is the result:\n```json\n{\"items\": [1, {\"b\": 2}]}\n```\nLet me know if you need more.";
+        assert_eq!(extract_json(answer).unwrap(), json!({ "items": [1, { "b": 2 }] }));
+        assert_eq!(extract_json("Sure! [\"x\", \"y\"] is the list.").unwrap(), json!(["x", "y"]));
+    }
+
+    #[test]
+    fn answers_without_json() {
+        assert_eq!(extract_json("I can't help with that.").unwrap_err(), "Response does not contain JSON");
+        assert!(extract_json("{ not json }").unwrap_err().starts_with("Response is not valid JSON"));
+    }
+}
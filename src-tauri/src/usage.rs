@@ -0,0 +1,508 @@
+// Local token usage and cost accounting
+// Every chat and transcription request appends a record to usage/usage.jsonl
+// in app data. Costs are estimates from a local price table, which can be
+// overridden per model in usage/prices.json.
+
+use base64::Engine;
+use chrono::{Datelike, Local, NaiveDate, TimeZone};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+use crate::error::ApiError;
+use crate::providers::ChatOutput;
+
+const DEFAULT_DAYS: u32 = 30;
+const DEFAULT_WEEKS: u32 = 12;
+
+// Serializes appends to the usage log
+static USAGE_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(default)]
+pub struct TokenUsage {
+    pub input_tokens: u64,
+    // Includes reasoning tokens, which are billed as output
+    pub output_tokens: u64,
+    pub reasoning_tokens: u64,
+    // Part of `input_tokens` served from the provider's prompt cache
+    pub cached_input_tokens: u64,
+    // Part of `input_tokens` written to the prompt cache, Anthropic bills
+    // these above the input price
+    pub cache_write_input_tokens: u64,
+}
+
+impl TokenUsage {
+    /// Providers report running totals, some split across events, so keep
+    /// the largest value seen for each count.
+    pub fn merge(&mut self, other: &TokenUsage) {
+        self.input_tokens = self.input_tokens.max(other.input_tokens);
+        self.output_tokens = self.output_tokens.max(other.output_tokens);
+        self.reasoning_tokens = self.reasoning_tokens.max(other.reasoning_tokens);
+        self.cached_input_tokens = self.cached_input_tokens.max(other.cached_input_tokens);
+        self.cache_write_input_tokens = self.cache_write_input_tokens.max(other.cache_write_input_tokens);
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum UsageKind {
+    Chat,
+    Transcription,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UsageRecord {
+    pub id: String,
+    pub timestamp_ms: u64,
+    pub session_id: Option<String>,
+    pub kind: UsageKind,
+    pub provider: String,
+    pub model: String,
+    #[serde(flatten)]
+    pub usage: TokenUsage,
+    pub audio_seconds: Option<f64>,
+    pub latency_ms: u64,
+    // None when the model isn't in the price table
+    pub cost_usd: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(default)]
+pub struct ModelPrice {
+    // USD per million tokens
+    pub input: f64,
+    pub output: f64,
+    // USD per million cached input tokens, falls back to `input`
+    pub cached_input: Option<f64>,
+    // USD per million tokens written to the prompt cache, falls back to `input`
+    pub cache_write_input: Option<f64>,
+    // USD per minute of transcribed audio
+    pub audio_minute: f64,
+}
+
+const fn price(input: f64, output: f64) -> ModelPrice {
+    ModelPrice {
+        input,
+        output,
+        cached_input: None,
+        cache_write_input: None,
+        audio_minute: 0.0,
+    }
+}
+
+// Anthropic bills cache reads at a tenth of the input price and 5 minute
+// cache writes at a quarter more
+const fn anthropic_price(input: f64, output: f64) -> ModelPrice {
+    ModelPrice {
+        input,
+        output,
+        cached_input: Some(input * 0.1),
+        cache_write_input: Some(input * 1.25),
+        audio_minute: 0.0,
+    }
+}
+
+// List prices in USD, matched by longest model name prefix
+const DEFAULT_PRICES: &[(&str, ModelPrice)] = &[
+    ("gpt-4o-mini", price(0.15, 0.6)),
+    ("gpt-4o", price(2.5, 10.0)),
+    ("gpt-4.1-nano", price(0.1, 0.4)),
+    ("gpt-4.1-mini", price(0.4, 1.6)),
+    ("gpt-4.1", price(2.0, 8.0)),
+    ("gpt-5-nano", price(0.05, 0.4)),
+    ("gpt-5-mini", price(0.25, 2.0)),
+    ("gpt-5", price(1.25, 10.0)),
+    ("o4-mini", price(1.1, 4.4)),
+    ("o3", price(2.0, 8.0)),
+    ("claude-3-5-haiku", anthropic_price(0.8, 4.0)),
+    ("claude-3-5-sonnet", anthropic_price(3.0, 15.0)),
+    ("claude-3-7-sonnet", anthropic_price(3.0, 15.0)),
+    ("claude-sonnet-4", anthropic_price(3.0, 15.0)),
+    ("claude-opus-4", anthropic_price(15.0, 75.0)),
+    ("gemini-2.0-flash", price(0.1, 0.4)),
+    ("gemini-2.5-flash-lite", price(0.1, 0.4)),
+    ("gemini-2.5-flash", price(0.3, 2.5)),
+    ("gemini-2.5-pro", price(1.25, 10.0)),
+    (
+        "whisper-1",
+        ModelPrice {
+            input: 0.0,
+            output: 0.0,
+            cached_input: None,
+            cache_write_input: None,
+            audio_minute: 0.006,
+        },
+    ),
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct UsageTotals {
+    // Local date, ISO week or session id depending on the grouping
+    pub key: String,
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub reasoning_tokens: u64,
+    pub audio_seconds: f64,
+    pub cost_usd: f64,
+    // Requests whose cost couldn't be estimated
+    pub unpriced_requests: u64,
+}
+
+impl UsageTotals {
+    fn add(&mut self, record: &UsageRecord) {
+        self.requests += 1;
+        self.input_tokens += record.usage.input_tokens;
+        self.output_tokens += record.usage.output_tokens;
+        self.reasoning_tokens += record.usage.reasoning_tokens;
+        self.audio_seconds += record.audio_seconds.unwrap_or(0.0);
+        match record.cost_usd {
+            Some(cost) => self.cost_usd += cost,
+            None => self.unpriced_requests += 1,
+        }
+    }
+}
+
+fn get_usage_dir(app: &AppHandle) -> Result<PathBuf, ApiError> {
+    let usage_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| ApiError::Storage(format!("Failed to get app data directory: {}", e)))?
+        .join("usage");
+
+    fs::create_dir_all(&usage_dir)
+        .map_err(|e| ApiError::Storage(format!("Failed to create usage directory: {}", e)))?;
+
+    Ok(usage_dir)
+}
+
+fn load_prices(app: &AppHandle) -> Vec<(String, ModelPrice)> {
+    let overrides: HashMap<String, ModelPrice> = get_usage_dir(app)
+        .ok()
+        .and_then(|dir| fs::read_to_string(dir.join("prices.json")).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+
+    let mut prices: Vec<(String, ModelPrice)> = DEFAULT_PRICES
+        .iter()
+        .filter(|(model, _)| !overrides.contains_key(*model))
+        .map(|(model, price)| (model.to_string(), *price))
+        .collect();
+    prices.extend(overrides);
+    prices
+}
+
+fn estimate_cost(prices: &[(String, ModelPrice)], model: &str, usage: &TokenUsage, audio_seconds: Option<f64>) -> Option<f64> {
+    let model = model.to_lowercase();
+    // Provider prefixes like "openai/gpt-4o" from OpenRouter
+    let name = model.rsplit('/').next().unwrap_or(&model);
+
+    let (_, price) = prices
+        .iter()
+        .filter(|(prefix, _)| name.starts_with(&prefix.to_lowercase()))
+        .max_by_key(|(prefix, _)| prefix.len())?;
+
+    let cached = usage.cached_input_tokens.min(usage.input_tokens);
+    let written = usage.cache_write_input_tokens.min(usage.input_tokens - cached);
+    let uncached = usage.input_tokens - cached - written;
+    let tokens = uncached as f64 * price.input
+        + cached as f64 * price.cached_input.unwrap_or(price.input)
+        + written as f64 * price.cache_write_input.unwrap_or(price.input)
+        + usage.output_tokens as f64 * price.output;
+
+    Some(tokens / 1_000_000.0 + audio_seconds.unwrap_or(0.0) / 60.0 * price.audio_minute)
+}
+
+fn append_record(app: &AppHandle, record: &UsageRecord) -> Result<(), ApiError> {
+    let line = serde_json::to_string(record)
+        .map_err(|e| ApiError::Storage(format!("Failed to serialize usage record: {}", e)))?;
+
+    let _lock = USAGE_LOCK.lock().unwrap();
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(get_usage_dir(app)?.join("usage.jsonl"))
+        .map_err(|e| ApiError::Storage(format!("Failed to open usage log: {}", e)))?;
+    writeln!(file, "{}", line).map_err(|e| ApiError::Storage(format!("Failed to write usage log: {}", e)))
+}
+
+fn read_records(app: &AppHandle) -> Result<Vec<UsageRecord>, ApiError> {
+    let _lock = USAGE_LOCK.lock().unwrap();
+    let path = get_usage_dir(app)?.join("usage.jsonl");
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(&path).map_err(|e| ApiError::Storage(format!("Failed to read usage log: {}", e)))?;
+    // Skip lines that can't be parsed, e.g. one cut short by a crash
+    Ok(content.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
+}
+
+/// Records one request. Accounting is best-effort and never fails the request.
+pub fn record(
+    app: &AppHandle,
+    kind: UsageKind,
+    provider: &str,
+    model: &str,
+    usage: TokenUsage,
+    audio_seconds: Option<f64>,
+    latency_ms: u64,
+) {
+    let session_id = app.state::<crate::UsageState>().session_id.lock().unwrap().clone();
+    let record = UsageRecord {
+        id: uuid::Uuid::new_v4().to_string(),
+        timestamp_ms: Local::now().timestamp_millis().max(0) as u64,
+        session_id,
+        kind,
+        provider: provider.to_string(),
+        model: model.to_string(),
+        cost_usd: estimate_cost(&load_prices(app), model, &usage, audio_seconds),
+        usage,
+        audio_seconds,
+        latency_ms,
+    };
+
+    if let Err(e) = append_record(app, &record) {
+        eprintln!("Failed to record usage: {}", e);
+    }
+}
+
+pub fn record_chat(app: &AppHandle, provider: &str, model: &str, output: &ChatOutput) {
+    record(
+        app,
+        UsageKind::Chat,
+        provider,
+        model,
+        output.usage.unwrap_or_default(),
+        None,
+        output.latency_ms,
+    );
+}
+
+/// Duration of base64 WAV audio, for backends that don't report it.
+pub fn wav_duration_seconds(audio_base64: &str) -> Option<f64> {
+    let bytes = base64::engine::general_purpose::STANDARD.decode(audio_base64).ok()?;
+    let reader = hound::WavReader::new(std::io::Cursor::new(bytes)).ok()?;
+    let sample_rate = reader.spec().sample_rate;
+    (sample_rate > 0).then(|| reader.duration() as f64 / sample_rate as f64)
+}
+
+fn totals_by<F>(records: &[UsageRecord], key: F) -> Vec<UsageTotals>
+where
+    F: Fn(&UsageRecord) -> Option<String>,
+{
+    let mut totals: BTreeMap<String, UsageTotals> = BTreeMap::new();
+    for record in records {
+        if let Some(key) = key(record) {
+            totals
+                .entry(key.clone())
+                .or_insert_with(|| UsageTotals { key, ..UsageTotals::default() })
+                .add(record);
+        }
+    }
+    totals.into_values().collect()
+}
+
+fn local_time(record: &UsageRecord) -> Option<chrono::DateTime<Local>> {
+    Local.timestamp_millis_opt(record.timestamp_ms as i64).single()
+}
+
+fn day_key(date: NaiveDate) -> String {
+    date.format("%Y-%m-%d").to_string()
+}
+
+// ISO weeks start on Monday and can belong to the neighbouring year
+fn week_key(date: NaiveDate) -> String {
+    let week = date.iso_week();
+    format!("{}-W{:02}", week.year(), week.week())
+}
+
+#[tauri::command]
+pub fn start_usage_session(app: AppHandle) -> String {
+    let session_id = uuid::Uuid::new_v4().to_string();
+    *app.state::<crate::UsageState>().session_id.lock().unwrap() = Some(session_id.clone());
+    session_id
+}
+
+/// Ends the current session, returning its id.
+#[tauri::command]
+pub fn end_usage_session(app: AppHandle) -> Option<String> {
+    app.state::<crate::UsageState>().session_id.lock().unwrap().take()
+}
+
+/// Totals per local day, oldest first, for the last `days` days.
+#[tauri::command]
+pub fn get_daily_usage(app: AppHandle, days: Option<u32>) -> Result<Vec<UsageTotals>, ApiError> {
+    let since = Local::now().date_naive() - chrono::Days::new(days.unwrap_or(DEFAULT_DAYS).saturating_sub(1) as u64);
+
+    Ok(totals_by(&read_records(&app)?, |record| {
+        let date = local_time(record)?.date_naive();
+        (date >= since).then(|| day_key(date))
+    }))
+}
+
+/// Totals per ISO week ("2025-W07"), oldest first, for the last `weeks` weeks.
+#[tauri::command]
+pub fn get_weekly_usage(app: AppHandle, weeks: Option<u32>) -> Result<Vec<UsageTotals>, ApiError> {
+    let today = Local::now().date_naive();
+    let this_week = today - chrono::Days::new(today.weekday().num_days_from_monday() as u64);
+    let since = this_week - chrono::Days::new(7 * weeks.unwrap_or(DEFAULT_WEEKS).saturating_sub(1) as u64);
+
+    Ok(totals_by(&read_records(&app)?, |record| {
+        let date = local_time(record)?.date_naive();
+        (date >= since).then(|| week_key(date))
+    }))
+}
+
+/// Totals per usage session, or for a single session when `session_id` is set.
+#[tauri::command]
+pub fn get_session_usage(app: AppHandle, session_id: Option<String>) -> Result<Vec<UsageTotals>, ApiError> {
+    Ok(totals_by(&read_records(&app)?, |record| {
+        record
+            .session_id
+            .clone()
+            .filter(|id| session_id.as_ref().is_none_or(|wanted| wanted == id))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_prices() -> Vec<(String, ModelPrice)> {
+        DEFAULT_PRICES.iter().map(|(model, price)| (model.to_string(), *price)).collect()
+    }
+
+    fn usage(input_tokens: u64, output_tokens: u64) -> TokenUsage {
+        TokenUsage { input_tokens, output_tokens, ..TokenUsage::default() }
+    }
+
+    fn cost(model: &str, usage: &TokenUsage) -> Option<f64> {
+        estimate_cost(&default_prices(), model, usage, None)
+    }
+
+    fn assert_close(actual: Option<f64>, expected: f64) {
+        let actual = actual.expect("model should be priced");
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    fn record_at(date: (i32, u32, u32), session_id: Option<&str>, cost_usd: Option<f64>) -> UsageRecord {
+        let timestamp = Local.with_ymd_and_hms(date.0, date.1, date.2, 12, 0, 0).single().unwrap();
+        UsageRecord {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp_ms: timestamp.timestamp_millis() as u64,
+            session_id: session_id.map(str::to_string),
+            kind: UsageKind::Chat,
+            provider: "openai".to_string(),
+            model: "gpt-4o".to_string(),
+            usage: usage(100, 10),
+            audio_seconds: None,
+            latency_ms: 0,
+            cost_usd,
+        }
+    }
+
+    fn by_day(records: &[UsageRecord]) -> Vec<UsageTotals> {
+        totals_by(records, |record| local_time(record).map(|time| day_key(time.date_naive())))
+    }
+
+    fn by_week(records: &[UsageRecord]) -> Vec<UsageTotals> {
+        totals_by(records, |record| local_time(record).map(|time| week_key(time.date_naive())))
+    }
+
+    #[test]
+    fn prices_match_the_longest_prefix() {
+        let million = usage(1_000_000, 1_000_000);
+        assert_close(cost("gpt-4o-mini-2024-07-18", &million), 0.75);
+        assert_close(cost("gpt-4o-2024-08-06", &million), 12.5);
+        assert_close(cost("openai/GPT-4o", &million), 12.5);
+        assert_eq!(cost("llama3", &million), None);
+        assert_eq!(cost("", &million), None);
+    }
+
+    #[test]
+    fn cache_reads_and_writes_are_priced_apart() {
+        let usage = TokenUsage {
+            input_tokens: 1_000_000,
+            cached_input_tokens: 400_000,
+            cache_write_input_tokens: 100_000,
+            ..TokenUsage::default()
+        };
+        // 500k uncached at $3, 400k read at $0.30, 100k written at $3.75
+        assert_close(cost("claude-sonnet-4-20250514", &usage), 1.5 + 0.12 + 0.375);
+        // Without cache prices every input token costs the same
+        assert_close(cost("gemini-2.5-pro", &usage), 1.25);
+
+        // Counts above the input total are clamped rather than going negative
+        let inflated = TokenUsage { input_tokens: 100, cached_input_tokens: 80, cache_write_input_tokens: 80, ..TokenUsage::default() };
+        assert_close(cost("claude-sonnet-4", &inflated), (80.0 * 0.3 + 20.0 * 3.75) / 1_000_000.0);
+    }
+
+    #[test]
+    fn audio_is_priced_per_minute() {
+        let cost = estimate_cost(&default_prices(), "whisper-1", &TokenUsage::default(), Some(90.0));
+        assert_close(cost, 0.009);
+    }
+
+    #[test]
+    fn merge_keeps_the_largest_counts() {
+        let mut total = TokenUsage { input_tokens: 120, cached_input_tokens: 100, ..TokenUsage::default() };
+        total.merge(&TokenUsage { output_tokens: 5, ..TokenUsage::default() });
+        total.merge(&TokenUsage { input_tokens: 90, output_tokens: 42, reasoning_tokens: 10, ..TokenUsage::default() });
+        assert_eq!(
+            total,
+            TokenUsage {
+                input_tokens: 120,
+                output_tokens: 42,
+                reasoning_tokens: 10,
+                cached_input_tokens: 100,
+                cache_write_input_tokens: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn totals_group_by_day() {
+        let records = vec![
+            record_at((2025, 3, 2), None, Some(0.5)),
+            record_at((2025, 3, 1), None, Some(0.25)),
+            record_at((2025, 3, 2), None, None),
+        ];
+        let totals = by_day(&records);
+
+        let keys: Vec<&str> = totals.iter().map(|t| t.key.as_str()).collect();
+        assert_eq!(keys, vec!["2025-03-01", "2025-03-02"]);
+        assert_eq!(totals[1].requests, 2);
+        assert_eq!(totals[1].input_tokens, 200);
+        assert_eq!(totals[1].cost_usd, 0.5);
+        assert_eq!(totals[1].unpriced_requests, 1);
+    }
+
+    #[test]
+    fn totals_group_by_iso_week() {
+        let records = vec![
+            // Sunday and Monday of consecutive weeks across the new year
+            record_at((2024, 12, 29), None, Some(1.0)),
+            record_at((2024, 12, 30), None, Some(1.0)),
+            record_at((2025, 1, 5), None, Some(1.0)),
+        ];
+        let totals = by_week(&records);
+
+        let keys: Vec<(&str, u64)> = totals.iter().map(|t| (t.key.as_str(), t.requests)).collect();
+        assert_eq!(keys, vec![("2024-W52", 1), ("2025-W01", 2)]);
+    }
+
+    #[test]
+    fn totals_skip_records_without_a_key() {
+        let records = vec![record_at((2025, 3, 1), Some("a"), None), record_at((2025, 3, 1), None, None)];
+        let totals = totals_by(&records, |record| record.session_id.clone());
+        assert_eq!(totals.len(), 1);
+        assert_eq!(totals[0].key, "a");
+    }
+}
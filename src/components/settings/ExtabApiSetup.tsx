@@ -8,8 +8,10 @@ import {
   CoffeeIcon,
 } from "lucide-react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { openUrl } from "@tauri-apps/plugin-opener";
 import { useApp } from "@/contexts";
+import { getErrorMessage } from "@/lib/utils";
 import {
   Command,
   CommandEmpty,
@@ -55,6 +57,19 @@ interface Model {
   isAvailable: boolean;
 }
 
+// Returned by fetch_models
+interface ModelCatalog {
+  models: Model[];
+  // Served from the local cache because the backend couldn't be reached
+  stale: boolean;
+  fetched_at: number;
+}
+
+interface SelectedModelUnavailableEvent {
+  model: Model;
+  reason: "removed" | "unavailable";
+}
+
 const LICENSE_KEY_STORAGE_KEY = "extab_license_key";
 const INSTANCE_ID_STORAGE_KEY = "extab_instance_id";
 const SELECTED_EXTAB_MODEL_STORAGE_KEY = "selected_extab_model";
@@ -71,6 +86,7 @@ export const ExtabApiSetup = () => {
   const [success, setSuccess] = useState<string | null>(null);
   const [models, setModels] = useState<Model[]>([]);
   const [isModelsLoading, setIsModelsLoading] = useState(false);
+  const [modelsStale, setModelsStale] = useState(false);
   const [selectedModel, setSelectedModel] = useState<Model | null>(null);
   const [isPopoverOpen, setIsPopoverOpen] = useState(false);
   const [searchValue, setSearchValue] = useState("");
@@ -86,6 +102,24 @@ export const ExtabApiSetup = () => {
     }
   }, []);
 
+  // The stored model was dropped from the catalog or disabled
+  useEffect(() => {
+    const unlisten = listen<SelectedModelUnavailableEvent>(
+      "selected_model_unavailable",
+      (event) => {
+        const { model, reason } = event.payload;
+        setError(
+          reason === "removed"
+            ? `${model.name} is no longer offered. Please select another model.`
+            : `${model.name} is currently unavailable. Please select another model.`
+        );
+      }
+    );
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
   // Scroll to top when search value changes
   useEffect(() => {
     if (commandListRef.current) {
@@ -96,8 +130,9 @@ export const ExtabApiSetup = () => {
   const fetchModels = async () => {
     setIsModelsLoading(true);
     try {
-      const fetchedModels = await invoke<Model[]>("fetch_models");
-      setModels(fetchedModels);
+      const catalog = await invoke<ModelCatalog>("fetch_models");
+      setModels(catalog.models);
+      setModelsStale(catalog.stale);
     } catch (error) {
       console.error("Failed to fetch models:", error);
     } finally {
@@ -188,7 +223,7 @@ export const ExtabApiSetup = () => {
       }
     } catch (err) {
       console.error("License activation failed:", err);
-      setError(typeof err === "string" ? err : "Failed to activate license");
+      setError(getErrorMessage(err, "Failed to activate license"));
     } finally {
       setIsLoading(false);
     }
@@ -270,7 +305,7 @@ export const ExtabApiSetup = () => {
       }
     } catch (err) {
       console.error("Failed to get checkout URL:", err);
-      setError(typeof err === "string" ? err : "Failed to get checkout URL");
+      setError(getErrorMessage(err, "Failed to get checkout URL"));
     } finally {
       setIsCheckoutLoading(false);
     }
@@ -301,6 +336,8 @@ export const ExtabApiSetup = () => {
 
   const description = isModelsLoading
     ? "Fetching the list of supported models..."
+    : modelsStale
+    ? "Showing the last saved list of models, the server couldn't be reached."
     : providerList
     ? `Access top models from providers like ${providerList}. and select smaller models for faster responses.`
     : "Explore all the models Extab supports.";
@@ -5,13 +5,40 @@ import {
   getByPath,
   getStreamingContent,
 } from "./common.function";
-import { Message, TYPE_PROVIDER } from "@/types";
+import {
+  ChatContentPart,
+  ChatMessage,
+  ChatStreamEvent,
+  Message,
+  TYPE_PROVIDER,
+} from "@/types";
 import { fetch as tauriFetch } from "@tauri-apps/plugin-http";
 import { invoke } from "@tauri-apps/api/core";
+import { getErrorMessage } from "@/lib/utils";
 import { listen } from "@tauri-apps/api/event";
 import curl2Json from "@bany/curl-to-json";
 import { shouldUseExtabAPI } from "./extab.api";
 
+function toChatMessage(message: Message): ChatMessage {
+  if (typeof message.content === "string") {
+    return {
+      role: message.role,
+      content: [{ type: "text", text: message.content }],
+    };
+  }
+
+  const content = message.content.flatMap((part): ChatContentPart[] => {
+    if (part.text !== undefined) {
+      return [{ type: "text", text: part.text }];
+    }
+    if (part.image_url?.url) {
+      return [{ type: "image", data: part.image_url.url }];
+    }
+    return [];
+  });
+  return { role: message.role, content };
+}
+
 // Extab AI streaming function
 async function* fetchExtabAIResponse(params: {
   systemPrompt?: string;
@@ -27,15 +54,11 @@ async function* fetchExtabAIResponse(params: {
       history = [],
     } = params;
 
-    // Convert history to the expected format
-    let historyString: string | undefined;
-    if (history.length > 0) {
-      const formattedHistory = history?.reverse()?.map((msg) => ({
-        role: msg.role,
-        content: [{ type: "text", text: msg.content }],
-      }));
-      historyString = JSON.stringify(formattedHistory);
-    }
+    // History arrives newest first, the backend trims it to the model's
+    // context window
+    const chatHistory: ChatMessage[] = [...history]
+      .reverse()
+      .map(toChatMessage);
 
     // Handle images - can be string or array
     let imageBase64: any = undefined;
@@ -43,16 +66,23 @@ async function* fetchExtabAIResponse(params: {
       imageBase64 = imagesBase64.length === 1 ? imagesBase64[0] : imagesBase64;
     }
 
-    // Set up streaming event listener
+    // Set up streaming event listeners, scoped to this request
+    const requestId = crypto.randomUUID();
     let streamComplete = false;
     const streamChunks: string[] = [];
 
-    const unlisten = await listen("chat_stream_chunk", (event) => {
-      const chunk = event.payload as string;
-      streamChunks.push(chunk);
+    const unlisten = await listen<ChatStreamEvent>("chat_stream_chunk", (event) => {
+      if (event.payload.request_id !== requestId) return;
+      streamChunks.push(event.payload.content);
+    });
+
+    const unlistenComplete = await listen<ChatStreamEvent>("chat_stream_complete", (event) => {
+      if (event.payload.request_id !== requestId) return;
+      streamComplete = true;
     });
 
-    const unlistenComplete = await listen("chat_stream_complete", () => {
+    const unlistenCancelled = await listen<ChatStreamEvent>("chat_stream_cancelled", (event) => {
+      if (event.payload.request_id !== requestId) return;
       streamComplete = true;
     });
 
@@ -62,7 +92,8 @@ async function* fetchExtabAIResponse(params: {
         userMessage,
         systemPrompt,
         imageBase64,
-        history: historyString,
+        history: chatHistory,
+        requestId,
       });
 
       // Yield chunks as they come in
@@ -85,9 +116,10 @@ async function* fetchExtabAIResponse(params: {
     } finally {
       unlisten();
       unlistenComplete();
+      unlistenCancelled();
     }
   } catch (error) {
-    const errorMessage = error instanceof Error ? error.message : String(error);
+    const errorMessage = getErrorMessage(error);
     yield `Extab API Error: ${errorMessage}`;
   }
 }
@@ -5,6 +5,7 @@ import {
 } from "./common.function";
 import { fetch as tauriFetch } from "@tauri-apps/plugin-http";
 import { invoke } from "@tauri-apps/api/core";
+import { getErrorMessage } from "@/lib/utils";
 
 import { TYPE_PROVIDER } from "@/types";
 import curl2Json from "@bany/curl-to-json";
@@ -31,7 +32,7 @@ async function fetchExtabSTT(audio: File | Blob): Promise<string> {
       return response.error || "Transcription failed";
     }
   } catch (error) {
-    const errorMessage = error instanceof Error ? error.message : String(error);
+    const errorMessage = getErrorMessage(error);
     return `Extab STT Error: ${errorMessage}`;
   }
 }
@@ -46,3 +46,13 @@ export const floatArrayToWav = (
 
   return new Blob([buffer], { type: `audio/${format}` });
 };
+
+// Error messages from invoke() can be strings, Errors or structured ApiErrors
+export const getErrorMessage = (error: unknown, fallback = "Unknown error") => {
+  if (typeof error === "string") return error;
+  if (error instanceof Error) return error.message;
+  if (error && typeof error === "object" && "message" in error) {
+    return String((error as { message: unknown }).message);
+  }
+  return fallback;
+};
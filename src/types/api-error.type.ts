@@ -0,0 +1,40 @@
+// Error shape returned by the Rust API commands (see src-tauri/src/error.rs)
+export type ApiErrorCode =
+  | "network"
+  | "timeout"
+  | "auth"
+  | "license"
+  | "quota"
+  | "server"
+  | "stream"
+  | "parse"
+  | "config"
+  | "storage"
+  | "cancelled"
+  | "validation";
+
+export interface ApiError {
+  code: ApiErrorCode;
+  http_status: number | null;
+  retryable: boolean;
+  message: string;
+}
+
+// Payload of the "api_retry" event, sent before each retry attempt
+export interface ApiRetryEvent {
+  context: string;
+  attempt: number;
+  max_attempts: number;
+  delay_ms: number;
+  error: ApiError;
+}
+
+// Payload of the "chat_stream_chunk", "chat_stream_reasoning",
+// "chat_stream_complete" and "chat_stream_cancelled" events
+export interface ChatStreamEvent {
+  request_id: string;
+  content: string;
+  // Set on "chat_stream_complete"
+  reasoning?: string;
+  tool_calls?: { id: string; name: string; arguments: Record<string, unknown> }[];
+}
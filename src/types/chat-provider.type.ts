@@ -0,0 +1,124 @@
+// Types for the native provider adapters (see src-tauri/src/providers)
+import { ApiError } from "./api-error.type";
+import { TokenUsage } from "./usage.type";
+
+export type ChatProviderKind = "openai" | "anthropic" | "gemini";
+
+export interface ChatProviderConfig {
+  kind: ChatProviderKind;
+  base_url?: string;
+  api_key?: string;
+  model: string;
+  headers?: Record<string, string>;
+}
+
+export interface ToolDefinition {
+  name: string;
+  description?: string;
+  // JSON Schema of the arguments object
+  parameters: Record<string, unknown>;
+}
+
+export interface ToolCall {
+  id: string;
+  name: string;
+  arguments: Record<string, unknown>;
+}
+
+export interface ToolResult {
+  tool_call_id: string;
+  name: string;
+  content: string;
+  is_error?: boolean;
+}
+
+export type ChatContentPart =
+  | { type: "text"; text: string }
+  | { type: "image"; data: string; mime_type?: string }
+  | ({ type: "tool_call" } & ToolCall)
+  | ({ type: "tool_result" } & ToolResult);
+
+export interface ChatMessage {
+  role: "system" | "user" | "assistant" | "tool";
+  content: ChatContentPart[];
+}
+
+export interface ReasoningOptions {
+  mode?: "default" | "enabled" | "disabled";
+  // Thinking token budget for Anthropic and Gemini
+  budget_tokens?: number;
+  // "low", "medium" or "high" for OpenAI-style reasoning models
+  effort?: string;
+  // Drop reasoning from events and output
+  hide?: boolean;
+}
+
+// OpenAI `response_format` shape, mapped for Anthropic and Gemini
+export type ResponseFormat =
+  | { type: "text" }
+  | { type: "json_object" }
+  | {
+      type: "json_schema";
+      json_schema: { name: string; schema: Record<string, unknown>; strict?: boolean };
+    };
+
+// `options` of chat_stream and provider_chat_stream, overrides for one call
+export interface GenerationOptions {
+  // Extab model id or name, or the provider's model name
+  model?: string;
+  temperature?: number;
+  max_tokens?: number;
+  top_p?: number;
+  stop?: string[];
+  response_format?: ResponseFormat;
+}
+
+// Returned by provider_chat_stream and submit_tool_results
+export interface ChatOutput {
+  content: string;
+  reasoning: string;
+  tool_calls: ToolCall[];
+  finish_reason: string | null;
+  usage: TokenUsage | null;
+  latency_ms: number;
+  time_to_first_token_ms: number | null;
+}
+
+// Payload of "chat_stream_tool_call", `arguments` is the JSON text so far
+export interface ChatStreamToolCallEvent {
+  request_id: string;
+  index: number;
+  id: string | null;
+  name: string | null;
+  arguments_delta: string;
+  arguments: string;
+}
+
+// One entry of compare_models `targets`. Without a provider the request goes
+// through Extab, `model` then being an Extab model id or name
+export interface ComparisonTarget {
+  provider?: ChatProviderConfig;
+  model?: string;
+  label?: string;
+}
+
+export interface ComparisonAnswer {
+  index: number;
+  // Request id of this answer's chat_stream events, "<request_id>:<index>"
+  request_id: string;
+  label: string;
+  provider: string;
+  model: string;
+}
+
+// Payload of "model_comparison_started"
+export interface ComparisonStartedEvent {
+  request_id: string;
+  answers: ComparisonAnswer[];
+}
+
+// Payload of "model_comparison_result", also returned by compare_models
+export interface ComparisonResult extends ComparisonAnswer {
+  output: ChatOutput | null;
+  error: ApiError | null;
+}
@@ -0,0 +1,36 @@
+// Backend endpoint configuration (see src-tauri/src/endpoints.rs)
+export interface EndpointSettings {
+  chat_endpoint?: string | null;
+  audio_endpoint?: string | null;
+  models_endpoint?: string | null;
+  payment_endpoint?: string | null;
+  // Omit to keep the saved key, empty string to clear it
+  api_access_key?: string | null;
+  auth_header?: string | null;
+  // Empty sends the bare key
+  auth_scheme?: string | null;
+  extra_headers?: Record<string, string>;
+}
+
+export type EndpointSource = "settings" | "env" | "default" | "unset";
+
+export interface ResolvedEndpoint {
+  value: string | null;
+  source: EndpointSource;
+}
+
+// Returned by set_endpoint_settings
+export interface EndpointStatus {
+  chat: ResolvedEndpoint;
+  audio: ResolvedEndpoint;
+  models: ResolvedEndpoint;
+  payment: ResolvedEndpoint;
+  api_access_key_source: EndpointSource;
+}
+
+// Returned by get_endpoint_settings, the key itself is never sent back
+export interface EndpointSettingsResponse {
+  settings: EndpointSettings;
+  has_api_access_key: boolean;
+  status: EndpointStatus;
+}
@@ -4,3 +4,7 @@ export * from "./context.type";
 export * from "./provider.type";
 export * from "./settings.hook";
 export * from "./completion";
+export * from "./api-error.type";
+export * from "./chat-provider.type";
+export * from "./usage.type";
+export * from "./endpoint.type";
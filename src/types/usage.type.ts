@@ -0,0 +1,23 @@
+// Usage accounting (see src-tauri/src/usage.rs)
+export interface TokenUsage {
+  input_tokens: number;
+  // Includes reasoning tokens
+  output_tokens: number;
+  reasoning_tokens: number;
+  cached_input_tokens: number;
+  // Part of input_tokens written to the prompt cache
+  cache_write_input_tokens: number;
+}
+
+// Returned by get_daily_usage, get_weekly_usage and get_session_usage
+export interface UsageTotals {
+  // Local date, ISO week or session id
+  key: string;
+  requests: number;
+  input_tokens: number;
+  output_tokens: number;
+  reasoning_tokens: number;
+  audio_seconds: number;
+  cost_usd: number;
+  unpriced_requests: number;
+}